@@ -0,0 +1,196 @@
+use id3::TagLike;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::editor;
+use crate::names;
+
+/// Width and height of the cached thumbnails in pixels.
+const THUMBNAIL_SIZE: u32 = 128;
+/// Images in an album folder that are used as its cover, in order of preference.
+const COVER_NAMES: [&str; 4] = ["cover", "folder", "front", "album"];
+const COVER_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+const AUDIO_EXTENSIONS: [&str; 3] = ["mp3", "wav", "aiff"];
+
+#[derive(Debug, Clone)]
+enum Source {
+    /// An image file next to the tracks of the album.
+    Image(PathBuf),
+    /// An audio file with the cover embedded as an id3 picture (APIC) frame.
+    Embedded(PathBuf),
+}
+
+/// Album art found in local audio files and music folders, keyed by the
+/// normalized (artist, album) names.
+#[derive(Debug, Default)]
+pub struct AlbumArt {
+    sources: HashMap<(String, String), Source>,
+    cache: PathBuf,
+}
+
+fn key(artist: &str, album: &str) -> (String, String) {
+    (names::key(artist), names::key(album))
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Returns the cover image among the `files` of a folder, if there is one.
+fn cover_image(files: &[PathBuf]) -> Option<&PathBuf> {
+    COVER_NAMES.iter().find_map(|name| {
+        files.iter().find(|file| {
+            has_extension(file, &COVER_EXTENSIONS)
+                && file
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .is_some_and(|stem| stem.eq_ignore_ascii_case(name))
+        })
+    })
+}
+
+impl AlbumArt {
+    /// Searches `folders` recursively for audio files with embedded covers
+    /// and for cover images next to tagged audio files. A cover image in a
+    /// folder without tagged files is assigned to the album named after the
+    /// folder, by the artist named after its parent (`Artist/Album/cover.jpg`).
+    pub fn scan(folders: &[PathBuf], cache: PathBuf) -> Self {
+        let mut art = Self {
+            sources: HashMap::new(),
+            cache,
+        };
+        let mut visited = HashSet::new();
+        for folder in folders {
+            art.scan_folder(folder, &mut visited);
+        }
+        art
+    }
+
+    /// Scans `folder` and its subfolders, skipping folders in `visited`, so
+    /// that symlinks pointing back up the tree don't lead into a loop.
+    fn scan_folder(&mut self, folder: &Path, visited: &mut HashSet<PathBuf>) {
+        let Ok(canonical) = fs::canonicalize(folder) else {
+            return;
+        };
+        if !visited.insert(canonical) {
+            return;
+        }
+        let Ok(read_dir) = fs::read_dir(folder) else {
+            return;
+        };
+        let mut files = vec![];
+        let mut subfolders = vec![];
+        for path in read_dir.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if path.is_dir() {
+                subfolders.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+        files.sort();
+        let cover = cover_image(&files).cloned();
+        let mut tagged = false;
+        for file in files
+            .iter()
+            .filter(|file| has_extension(file, &AUDIO_EXTENSIONS))
+        {
+            let Ok(tag) = id3::Tag::read_from_path(file) else {
+                continue;
+            };
+            let (Some(artist), Some(album)) = (tag.album_artist().or(tag.artist()), tag.album())
+            else {
+                continue;
+            };
+            tagged = true;
+            let source = match &cover {
+                Some(cover) => Source::Image(cover.clone()),
+                None if tag.pictures().next().is_some() => Source::Embedded(file.clone()),
+                None => continue,
+            };
+            self.sources.entry(key(artist, album)).or_insert(source);
+        }
+        if let (Some(cover), false) = (cover, tagged) {
+            let name = |path: Option<&Path>| {
+                path.and_then(|path| path.file_name())
+                    .map(|name| name.to_string_lossy().into_owned())
+            };
+            if let (Some(album), Some(artist)) = (name(Some(folder)), name(folder.parent())) {
+                self.sources
+                    .entry(key(&artist, &album))
+                    .or_insert(Source::Image(cover));
+            }
+        }
+        subfolders.sort();
+        for subfolder in subfolders {
+            self.scan_folder(&subfolder, visited);
+        }
+    }
+
+    pub fn contains(&self, artist: &str, album: &str) -> bool {
+        self.sources.contains_key(&key(artist, album))
+    }
+
+    /// Returns the path of the thumbnail of the cover of an album, creating
+    /// it in the cache folder if it doesn't exist yet. Thumbnails are named
+    /// after the source file and its modification time as well, so that a
+    /// changed cover gets a new thumbnail.
+    pub fn thumbnail(&self, artist: &str, album: &str) -> Option<PathBuf> {
+        let source = self.sources.get(&key(artist, album))?;
+        let (artist, album) = key(artist, album);
+        let (Source::Image(file) | Source::Embedded(file)) = source;
+        let modified = fs::metadata(file)
+            .and_then(|metadata| metadata.modified())
+            .ok()?;
+        let path = self.cache.join(format!(
+            "{}.jpg",
+            editor::content_version(&format!(
+                "{}\n{}\n{}\n{:?}",
+                artist,
+                album,
+                file.display(),
+                modified
+            ))
+        ));
+        if path.exists() {
+            return Some(path);
+        }
+        let data = match source {
+            Source::Image(image) => fs::read(image).ok()?,
+            Source::Embedded(file) => {
+                let tag = id3::Tag::read_from_path(file).ok()?;
+                let picture = tag
+                    .pictures()
+                    .find(|picture| picture.picture_type == id3::frame::PictureType::CoverFront)
+                    .or(tag.pictures().next())?;
+                picture.data.clone()
+            }
+        };
+        let thumbnail = image::load_from_memory(&data)
+            .ok()?
+            .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+            .into_rgb8();
+        fs::create_dir_all(&self.cache).ok()?;
+        thumbnail.save(&path).ok()?;
+        Some(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scans_folders_with_symlink_loops() {
+        let library = std::env::temp_dir().join(format!("journal-art-test-{}", std::process::id()));
+        let album = library.join("Artist").join("Album");
+        fs::create_dir_all(&album).unwrap();
+        fs::write(album.join("cover.jpg"), []).unwrap();
+        std::os::unix::fs::symlink(&library, album.join("library")).unwrap();
+        let art = AlbumArt::scan(std::slice::from_ref(&library), library.join("cache"));
+        fs::remove_dir_all(&library).unwrap();
+        assert!(art.contains("artist", "album"));
+    }
+}
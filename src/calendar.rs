@@ -0,0 +1,153 @@
+use chrono::{Datelike, NaiveDate};
+use std::collections::HashMap;
+
+/// What the color intensity of a calendar day represents.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shading {
+    TextLength,
+    MediaCount,
+}
+
+impl Shading {
+    pub fn from_param(param: Option<&str>) -> Self {
+        match param {
+            Some("media") => Shading::MediaCount,
+            _ => Shading::TextLength,
+        }
+    }
+
+    pub fn param(&self) -> &'static str {
+        match self {
+            Shading::TextLength => "length",
+            Shading::MediaCount => "media",
+        }
+    }
+}
+
+/// The number of items a day data provider has for a day.
+#[derive(Debug, Clone)]
+pub struct DayDataCount {
+    pub icon: &'static str,
+    /// Describes the count, e.g. "tracks played".
+    pub label: &'static str,
+    pub count: usize,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct DayStats {
+    pub text_length: usize,
+    pub num_pics: usize,
+    pub num_audio: usize,
+    pub day_data: Vec<DayDataCount>,
+}
+
+impl DayStats {
+    /// Returns the shading level between 1 and 4 for a day with an entry.
+    fn level(&self, shading: Shading) -> usize {
+        let (value, thresholds) = match shading {
+            Shading::TextLength => (self.text_length, [200, 800, 2000]),
+            Shading::MediaCount => (self.num_pics + self.num_audio, [1, 4, 10]),
+        };
+        1 + thresholds
+            .iter()
+            .filter(|threshold| value >= **threshold)
+            .count()
+    }
+
+    fn icons(&self) -> String {
+        [(self.num_pics > 0, "📷"), (self.num_audio > 0, "🎙")]
+            .into_iter()
+            .chain(self.day_data.iter().map(|data| (data.count > 0, data.icon)))
+            .filter(|(present, _)| *present)
+            .map(|(_, icon)| icon)
+            .collect()
+    }
+
+    fn description(&self) -> String {
+        let mut description = format!(
+            "{} characters, {} pictures, {} audio files",
+            self.text_length, self.num_pics, self.num_audio
+        );
+        for data in &self.day_data {
+            description.push_str(&format!(", {} {}", data.count, data.label));
+        }
+        description
+    }
+}
+
+pub const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAY_NAMES: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+
+fn days_of_month(year: i32, month: u32) -> impl Iterator<Item = NaiveDate> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1);
+    first
+        .into_iter()
+        .flat_map(|first| first.iter_days())
+        .take_while(move |day| day.month() == month)
+}
+
+/// Renders one month as a grid of weeks starting on monday. Days with an
+/// entry are shaded according to `shading` and link to the entry. In the
+/// detailed (month view) variant, each day additionally shows its icons.
+pub fn month_html(
+    year: i32,
+    month: u32,
+    days: &HashMap<NaiveDate, DayStats>,
+    shading: Shading,
+    detailed: bool,
+) -> String {
+    let mut cells: Vec<String> = WEEKDAY_NAMES
+        .iter()
+        .map(|name| format!(r#"<div class="weekday">{}</div>"#, name))
+        .collect();
+    for (i, day) in days_of_month(year, month).enumerate() {
+        if i == 0 {
+            let offset = day.weekday().num_days_from_monday();
+            cells.extend((0..offset).map(|_| r#"<div class="day empty"></div>"#.to_owned()));
+        }
+        let date = day.format("%Y-%m-%d");
+        let cell = match days.get(&day) {
+            Some(stats) => {
+                let icons = if detailed {
+                    format!(r#"<span class="icons">{}</span>"#, stats.icons())
+                } else {
+                    String::new()
+                };
+                format!(
+                    r#"<a href="/{}" class="day level-{}" title="{}: {}"><span class="day-number">{}</span>{}</a>"#,
+                    date,
+                    stats.level(shading),
+                    date,
+                    stats.description(),
+                    day.day(),
+                    icons
+                )
+            }
+            None => format!(
+                r#"<div class="day" title="{}"><span class="day-number">{}</span></div>"#,
+                date,
+                day.day()
+            ),
+        };
+        cells.push(cell);
+    }
+    format!(
+        r#"<div class="month{}">{}</div>"#,
+        if detailed { " detailed" } else { "" },
+        cells.join("")
+    )
+}
@@ -1,2 +1,63 @@
-pub const JOURNAL_PATH: &'static str = "/home/toni/resource/journal";
-pub const DUMP_FOLDER_PATH: &'static str = "/home/toni/resource/journal/dump";
+pub const JOURNAL_PATH: &str = "/home/toni/resource/journal";
+pub const DUMP_FOLDER_PATH: &str = "/home/toni/resource/journal/dump";
+/// A Last.fm account whose scrobbles are shown alongside the entries.
+pub struct LastFmAccount {
+    pub username: &'static str,
+    /// The export of the account in the journal folder, which is also where
+    /// newly synced scrobbles are stored. Besides Last.fm exports, this can
+    /// be a ListenBrainz, Spotify or Apple Music export (see
+    /// `scrobble_import::Format`), or a folder of several such files.
+    pub file: &'static str,
+}
+
+pub const LASTFM_ACCOUNTS: &[LastFmAccount] = &[LastFmAccount {
+    username: "Tehforsch",
+    file: "lastfmstats-Tehforsch.json",
+}];
+/// Whether the scrobbles of all accounts are shown as one combined music
+/// section or as one section per account.
+pub const LASTFM_MERGE_ACCOUNTS: bool = false;
+/// After how many days without scrobbles the data of an account is
+/// reported as outdated.
+pub const LASTFM_STALE_DAYS: i64 = 7;
+/// Base url of the Last.fm API. Point this at a local server to test the sync.
+pub const LASTFM_API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+/// Key for the Last.fm API. New scrobbles are only fetched if this is set.
+pub const LASTFM_API_KEY: Option<&str> = None;
+/// How often new scrobbles are fetched while the server is running.
+pub const LASTFM_SYNC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30 * 60);
+
+/// Alternative spellings of artist, album and track names, each mapped to
+/// the name it is counted and shown as, e.g. `("Beatles", "The Beatles")`.
+/// Differences in case and edition suffixes such as "(Remastered 2011)" are
+/// ignored without an alias.
+pub const NAME_ALIASES: &[(&str, &str)] = &[];
+
+/// A folder with the local music collection, which is searched for album
+/// art, either embedded in the audio files or as `cover.jpg` and similar
+/// images next to them. The audio files in the journal are always searched.
+pub const MUSIC_LIBRARY_PATH: Option<&str> = None;
+/// Where thumbnails of album art are cached.
+pub const ALBUM_ART_CACHE_PATH: &str = "/home/toni/.cache/journal/album-art";
+
+/// How long to wait for further changes after the journal changed on disk
+/// before reloading, so that e.g. copying many files causes one reload.
+pub const WATCH_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// How many days an "on this day" entry may be away from the actual date
+/// for years without an entry on exactly that day.
+pub const ON_THIS_DAY_TOLERANCE_DAYS: i64 = 3;
+
+/// Template for newly created entries. The placeholders `{date}`,
+/// `{weekday}`, `{weather}` and `{prompt}` are filled in on creation.
+pub const ENTRY_TEMPLATE: &str = "# {date}\n\n";
+/// Shell command whose output is used for the `{weather}` placeholder.
+pub const WEATHER_COMMAND: Option<&str> = None;
+/// One of these is picked at random for the `{prompt}` placeholder.
+pub const WRITING_PROMPTS: &[&str] = &[
+    "What happened today?",
+    "What are you grateful for today?",
+    "What did you learn today?",
+    "Who did you spend time with today?",
+    "What is on your mind right now?",
+];
@@ -0,0 +1,87 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use rouille::{Request, Response};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use tera::Tera;
+
+use crate::entries::{Entries, Entry};
+use crate::lastfm_provider::LastFmProvider;
+
+/// What providers get to see of the journal when rendering.
+pub struct Site<'a> {
+    pub entries: &'a Entries,
+    pub tera: &'a Tera,
+}
+
+/// A source of data about each day from outside of the journal, such as
+/// the music listened to, which is shown alongside the entries.
+///
+/// Providers are loaded once and keep their data behind interior
+/// mutability, so that they can be reloaded while the server is running.
+pub trait DayDataProvider: Send + Sync {
+    /// The name used in messages about the provider.
+    fn name(&self) -> &'static str;
+
+    /// (Re-)reads the data from disk.
+    fn load(&self);
+
+    /// Names of the files and folders in the root of the journal that the
+    /// provider keeps its data in, which are not entries.
+    fn journal_files(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// Problems found while loading the data, which are shown on the
+    /// dashboard and the health page.
+    fn status(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// How often `fetch` is run while the server is running, if at all.
+    fn fetch_interval(&self) -> Option<std::time::Duration> {
+        None
+    }
+
+    /// Fetches new data from an external service into local storage and
+    /// returns the number of new items. The data is reloaded afterwards if
+    /// there are any.
+    fn fetch(&self) -> Result<usize, String> {
+        Ok(0)
+    }
+
+    /// The icon shown for days with data in the calendar and the
+    /// description of the number of items, e.g. "tracks played".
+    fn calendar_label(&self) -> (&'static str, &'static str);
+
+    /// Returns the number of items on each day between `from` and `to`
+    /// (inclusive) that has any.
+    fn counts(&self, from: NaiveDate, to: NaiveDate) -> BTreeMap<NaiveDate, usize>;
+
+    /// Text to find each of `dates` (as `YYYY-MM-DD`) by in the search.
+    fn search_text(&self, _dates: &[NaiveDate]) -> HashMap<String, Vec<String>> {
+        HashMap::new()
+    }
+
+    /// Renders the section shown next to an entry, given the capture times
+    /// of its pictures.
+    fn section_html(
+        &self,
+        entry: &Entry,
+        capture_times: &HashMap<PathBuf, NaiveDateTime>,
+    ) -> Option<String>;
+
+    /// A note shown below a picture taken at `time`, as HTML.
+    fn photo_annotation(&self, _time: NaiveDateTime) -> Option<String> {
+        None
+    }
+
+    /// Handles requests for the pages of the provider.
+    fn response(&self, _request: &Request, _site: &Site) -> Option<Response> {
+        None
+    }
+}
+
+/// All available providers, not loaded yet.
+pub fn providers() -> Vec<Box<dyn DayDataProvider>> {
+    vec![Box::new(LastFmProvider::default())]
+}
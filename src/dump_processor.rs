@@ -1,8 +1,10 @@
 use crate::config;
-use chrono::{DateTime, NaiveDateTime, Utc};
+use crate::new_entry;
+use crate::photo_info;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, Utc};
 use id3::TagLike;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 pub fn process_dump_folder() -> Result<(), Box<dyn std::error::Error>> {
     let dump_path = Path::new(config::DUMP_FOLDER_PATH);
@@ -20,9 +22,8 @@ pub fn process_dump_folder() -> Result<(), Box<dyn std::error::Error>> {
         let path = entry.path();
 
         if path.is_file() {
-            match process_file(&path) {
+            match import_file(&path, None) {
                 Ok(target_date) => {
-                    move_file_to_date_folder(&path, &target_date)?;
                     println!("Moved {} to {}", path.display(), target_date);
                 }
                 Err(e) => {
@@ -35,6 +36,92 @@ pub fn process_dump_folder() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Moves a file into the entry for `date`, or for the date detected from
+/// the file itself if no date is given. Returns the date of the entry.
+pub fn import_file(
+    file_path: &Path,
+    date: Option<&str>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if is_note(file_path) {
+        return import_note(file_path, date);
+    }
+    let target_date = match date {
+        Some(date) => date.to_owned(),
+        None => process_file(file_path)?,
+    };
+    move_file_to_date_folder(file_path, &target_date)?;
+    Ok(target_date)
+}
+
+fn is_note(file_path: &Path) -> bool {
+    let extension = file_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|s| s.to_lowercase());
+    matches!(extension.as_deref(), Some("md") | Some("txt"))
+}
+
+pub fn is_supported(file_path: &Path) -> bool {
+    is_note(file_path) || target_subfolder(file_path).is_some()
+}
+
+/// Moves a text file into the folder of its day (by local modification
+/// time, unless `date` is given) as a note named after the time it was
+/// written, such as `2140-thoughts.md`.
+fn import_note(file_path: &Path, date: Option<&str>) -> Result<String, Box<dyn std::error::Error>> {
+    let modified: DateTime<Local> = fs::metadata(file_path)?.modified()?.into();
+    let modified = modified.naive_local();
+    let date = match date {
+        Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d")?,
+        None => modified.date(),
+    };
+    let entry_md_path = new_entry::create_stub(date)?;
+    let date_folder = entry_md_path.parent().unwrap();
+    let file_name = note_file_name(file_path, modified).ok_or("Invalid file name")?;
+    fs::rename(file_path, unique_path(date_folder, &file_name))?;
+    Ok(date.format("%Y-%m-%d").to_string())
+}
+
+fn note_file_name(file_path: &Path, written: NaiveDateTime) -> Option<String> {
+    let label = file_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())?
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-");
+    Some(format!("{}-{}.md", written.format("%H%M"), label))
+}
+
+pub fn target_subfolder(file_path: &Path) -> Option<&'static str> {
+    let extension = file_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|s| s.to_lowercase());
+
+    match extension.as_deref() {
+        Some("jpg") | Some("jpeg") | Some("png") | Some("tiff") | Some("tif") => Some("pics"),
+        Some("mp3") | Some("m4a") | Some("flac") | Some("wav") => Some("audio"),
+        _ => None,
+    }
+}
+
+/// Returns a path for `file_name` in `folder` that does not exist yet,
+/// appending a counter to the file stem if necessary.
+pub fn unique_path(folder: &Path, file_name: &str) -> PathBuf {
+    let path = folder.join(file_name);
+    let (stem, extension) = match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (file_name, String::new()),
+    };
+    (1..)
+        .map(|i| match i {
+            1 => path.clone(),
+            i => folder.join(format!("{}-{}{}", stem, i, extension)),
+        })
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
 fn process_file(file_path: &Path) -> Result<String, Box<dyn std::error::Error>> {
     let extension = file_path
         .extension()
@@ -50,37 +137,31 @@ fn process_file(file_path: &Path) -> Result<String, Box<dyn std::error::Error>>
     }
 }
 
+/// Reads the recording time of an mp3 file from its ID3 tag, if it
+/// contains at least the hour and minute.
+pub fn audio_recording_time(file_path: &Path) -> Option<NaiveDateTime> {
+    let extension = file_path.extension()?.to_str()?.to_lowercase();
+    if extension != "mp3" {
+        return None;
+    }
+    let date_recorded = id3::Tag::read_from_path(file_path).ok()?.date_recorded()?;
+    NaiveDate::from_ymd_opt(
+        date_recorded.year,
+        date_recorded.month? as u32,
+        date_recorded.day? as u32,
+    )?
+    .and_hms_opt(
+        date_recorded.hour? as u32,
+        date_recorded.minute? as u32,
+        date_recorded.second.unwrap_or(0) as u32,
+    )
+}
+
 fn extract_photo_date(file_path: &Path) -> Result<String, Box<dyn std::error::Error>> {
     // Try to extract EXIF date first
-    if let Ok(file) = std::fs::File::open(file_path) {
-        let mut bufreader = std::io::BufReader::new(&file);
-        if let Ok(exifreader) = exif::Reader::new().read_from_container(&mut bufreader) {
-            // Try different date fields in order of preference
-            let date_fields = [
-                exif::Tag::DateTimeOriginal,
-                exif::Tag::DateTime,
-                exif::Tag::DateTimeDigitized,
-            ];
-
-            for &tag in &date_fields {
-                if let Some(field) = exifreader.get_field(tag, exif::In::PRIMARY) {
-                    if let exif::Value::Ascii(ref vec) = field.value {
-                        if let Some(ascii_val) = vec.first() {
-                            let date_str = std::str::from_utf8(ascii_val)
-                                .map_err(|_| "Invalid UTF-8 in EXIF date")?;
-
-                            // EXIF dates are in format "YYYY:MM:DD HH:MM:SS"
-                            if let Ok(naive_dt) =
-                                NaiveDateTime::parse_from_str(date_str, "%Y:%m:%d %H:%M:%S")
-                            {
-                                let dt = DateTime::<Utc>::from_naive_utc_and_offset(naive_dt, Utc);
-                                return Ok(dt.format("%Y-%m-%d").to_string());
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    if let Some(naive_dt) = photo_info::photo_capture_time(file_path) {
+        let dt = DateTime::<Utc>::from_naive_utc_and_offset(naive_dt, Utc);
+        return Ok(dt.format("%Y-%m-%d").to_string());
     }
 
     // Fall back to file modification time if EXIF extraction fails
@@ -90,20 +171,17 @@ fn extract_photo_date(file_path: &Path) -> Result<String, Box<dyn std::error::Er
 fn extract_audio_date(file_path: &Path) -> Result<String, Box<dyn std::error::Error>> {
     if let Some(extension) = file_path.extension().and_then(|ext| ext.to_str()) {
         if extension.to_lowercase() == "mp3" {
-            match id3::Tag::read_from_path(file_path) {
-                Ok(tag) => {
-                    if let Some(date_recorded) = tag.date_recorded() {
-                        let year = date_recorded.year;
-                        let month = date_recorded.month.unwrap_or(1);
-                        let day = date_recorded.day.unwrap_or(1);
-                        return Ok(format!("{:04}-{:02}-{:02}", year, month, day));
-                    }
-
-                    if let Some(year) = tag.year() {
-                        return Ok(format!("{:04}-01-01", year));
-                    }
+            if let Ok(tag) = id3::Tag::read_from_path(file_path) {
+                if let Some(date_recorded) = tag.date_recorded() {
+                    let year = date_recorded.year;
+                    let month = date_recorded.month.unwrap_or(1);
+                    let day = date_recorded.day.unwrap_or(1);
+                    return Ok(format!("{:04}-{:02}-{:02}", year, month, day));
+                }
+
+                if let Some(year) = tag.year() {
+                    return Ok(format!("{:04}-01-01", year));
                 }
-                Err(_) => {}
             }
         }
     }
@@ -122,37 +200,44 @@ fn move_file_to_date_folder(
     file_path: &Path,
     date_str: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let journal_path = Path::new(config::JOURNAL_PATH);
-    let date_folder = journal_path.join(date_str);
+    let target_subfolder = target_subfolder(file_path).ok_or("Unsupported file type")?;
+    let date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d")?;
+    let entry_md_path = new_entry::create_stub(date)?;
+    let date_folder = entry_md_path.parent().unwrap();
 
-    if !date_folder.exists() {
-        fs::create_dir_all(&date_folder)?;
-
-        let entry_md_path = date_folder.join("entry.md");
-        if !entry_md_path.exists() {
-            fs::write(&entry_md_path, format!("# {}\n\n", date_str))?;
-        }
-    }
-
-    let file_name = file_path.file_name().unwrap();
-    let extension = file_path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map(|s| s.to_lowercase());
-
-    let target_subfolder = match extension.as_deref() {
-        Some("jpg") | Some("jpeg") | Some("png") | Some("tiff") | Some("tif") => "pics",
-        Some("mp3") | Some("m4a") | Some("flac") | Some("wav") => "audio",
-        _ => return Err("Unsupported file type".into()),
-    };
+    let file_name = file_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or("Invalid file name")?;
 
     let target_folder = date_folder.join(target_subfolder);
     if !target_folder.exists() {
         fs::create_dir_all(&target_folder)?;
     }
 
-    let target_path = target_folder.join(file_name);
+    let target_path = unique_path(&target_folder, file_name);
     fs::rename(file_path, target_path)?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_notes_after_the_time_they_were_written() {
+        let written = NaiveDate::from_ymd_opt(2024, 3, 5)
+            .unwrap()
+            .and_hms_opt(21, 40, 59)
+            .unwrap();
+        assert_eq!(
+            note_file_name(Path::new("dump/some  thoughts.md"), written).as_deref(),
+            Some("2140-some-thoughts.md")
+        );
+        assert_eq!(
+            note_file_name(Path::new("dump/todo.txt"), written).as_deref(),
+            Some("2140-todo.md")
+        );
+    }
+}
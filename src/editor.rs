@@ -0,0 +1,121 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum SaveError {
+    /// The file was changed on disk since the editor was opened.
+    Conflict {
+        current: String,
+    },
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Conflict { .. } => write!(f, "The entry was modified in the meantime"),
+            SaveError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(e: std::io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+/// Returns a version string identifying `content`, which the editor sends
+/// back on save to detect concurrent modifications. This is an FNV-1a hash,
+/// so it stays the same across restarts of the server.
+pub fn content_version(content: &str) -> String {
+    let hash = content.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// Writes `content` to `path`, unless the file on disk no longer matches
+/// `expected_version`. The content is written to a temporary file next to
+/// `path` first and then renamed over it, so a crash never leaves a
+/// half-written entry behind.
+pub fn save(path: &Path, content: &str, expected_version: &str) -> Result<(), SaveError> {
+    let current = match fs::read_to_string(path) {
+        Ok(current) => current,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    if content_version(&current) != expected_version {
+        return Err(SaveError::Conflict { current });
+    }
+    write_atomically(path, content)?;
+    Ok(())
+}
+
+/// Writes `content` to a hidden temporary file next to `path` and renames it
+/// over `path`.
+pub fn write_atomically(path: &Path, content: &str) -> std::io::Result<()> {
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("entry.md");
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str) -> std::path::PathBuf {
+        let folder = std::env::temp_dir().join(format!(
+            "journal-editor-{}-test-{}",
+            name,
+            std::process::id()
+        ));
+        fs::create_dir_all(&folder).unwrap();
+        folder.join("entry.md")
+    }
+
+    #[test]
+    fn saves_when_the_version_matches() {
+        let path = temp_file("match");
+        fs::write(&path, "old").unwrap();
+        let result = save(&path, "new", &content_version("old"));
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert!(result.is_ok());
+        assert_eq!(content, "new");
+    }
+
+    #[test]
+    fn refuses_to_overwrite_changes_made_in_the_meantime() {
+        let path = temp_file("conflict");
+        fs::write(&path, "changed elsewhere").unwrap();
+        let result = save(&path, "new", &content_version("old"));
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        match result {
+            Err(SaveError::Conflict { current }) => assert_eq!(current, "changed elsewhere"),
+            _ => panic!("expected a conflict"),
+        }
+        assert_eq!(content, "changed elsewhere");
+    }
+
+    #[test]
+    fn treats_a_missing_file_as_empty() {
+        let path = temp_file("missing");
+        let created = save(&path, "new", &content_version(""));
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let conflict = save(&path, "newer", &content_version("new"));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert!(created.is_ok());
+        assert_eq!(content, "new");
+        assert!(matches!(conflict, Err(SaveError::Conflict { current }) if current.is_empty()));
+    }
+}
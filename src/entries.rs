@@ -1,37 +1,263 @@
+use chrono::{Datelike, NaiveDate, NaiveTime};
 use rand::prelude::SliceRandom;
+use std::collections::{BTreeMap, HashMap};
 use std::io::Result;
 use std::path::{Path, PathBuf};
 
 use crate::config;
+use crate::front_matter::{self, FrontMatter};
+use crate::links;
+use crate::media_info::{self, MediaInfo, MediaInfos};
+use crate::tags;
 
-#[derive(Debug, PartialEq)]
+/// The largest tolerance of "on this day" queries. Years are at least 365
+/// days apart, so windows of up to 182 days around the same day of
+/// neighbouring years never overlap.
+const MAX_ON_THIS_DAY_TOLERANCE_DAYS: i64 = 182;
+
+/// An additional text file in a date folder besides `entry.md`. Notes
+/// named like `0830-morning.md` get the time and label from their name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Note {
+    path: PathBuf,
+    time: Option<NaiveTime>,
+    label: Option<String>,
+}
+
+impl Note {
+    fn new(path: PathBuf, name: &str) -> Self {
+        let stem = name.trim_end_matches(".md");
+        let (prefix, rest) = match stem.split_once('-') {
+            Some((prefix, rest)) => (prefix, Some(rest)),
+            None => (stem, None),
+        };
+        let time = NaiveTime::parse_from_str(prefix, "%H%M")
+            .ok()
+            .filter(|_| prefix.len() == 4);
+        let label = match time {
+            Some(_) => rest,
+            None => Some(stem),
+        }
+        .map(|label| label.replace(['-', '_'], " "))
+        .filter(|label| !label.is_empty());
+        Self { path, time, label }
+    }
+
+    pub fn content(&self) -> Result<String> {
+        std::fs::read_to_string(&self.path)
+    }
+
+    pub fn body(&self) -> Result<String> {
+        let content = self.content()?;
+        Ok(front_matter::split(&content).1.to_owned())
+    }
+
+    pub fn time(&self) -> Option<NaiveTime> {
+        self.time
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Entry {
+    date: NaiveDate,
     text_path: PathBuf,
+    /// Further notes of the day, timestamped ones in order of their time first.
+    notes: Vec<Note>,
+    front_matter: FrontMatter,
+    tags: Vec<String>,
+    links: Vec<String>,
+    /// Number of characters in the entry text, without front matter.
+    text_length: usize,
     pics: Vec<PathBuf>,
     audio: Vec<PathBuf>,
+    media_infos: MediaInfos,
+}
+
+/// Something unexpected found while reading the journal. None of these
+/// prevent the journal from loading, they are shown on the `/health` page.
+#[derive(Debug, Clone)]
+pub enum ScanWarning {
+    /// A file or folder in the journal root that is not a `YYYY-MM-DD` folder.
+    NotAnEntry(PathBuf),
+    NonUtf8Name(PathBuf),
+    MissingText(PathBuf),
+    UnreadableText(PathBuf, String),
+    InvalidFrontMatter(PathBuf, String),
+    InvalidMediaInfo(PathBuf, String),
+    Unreadable(PathBuf, String),
 }
 
-fn get_all_files_in_folder(path: &Path) -> Vec<PathBuf> {
+impl ScanWarning {
+    fn path(&self) -> &Path {
+        match self {
+            ScanWarning::NotAnEntry(path)
+            | ScanWarning::NonUtf8Name(path)
+            | ScanWarning::MissingText(path)
+            | ScanWarning::UnreadableText(path, _)
+            | ScanWarning::InvalidFrontMatter(path, _)
+            | ScanWarning::InvalidMediaInfo(path, _)
+            | ScanWarning::Unreadable(path, _) => path,
+        }
+    }
+}
+
+impl std::fmt::Display for ScanWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScanWarning::NotAnEntry(path) => {
+                write!(f, "{}: not a YYYY-MM-DD entry folder", path.display())
+            }
+            ScanWarning::NonUtf8Name(path) => {
+                write!(f, "{}: file name is not valid UTF-8", path.display())
+            }
+            ScanWarning::MissingText(path) => write!(f, "{}: missing entry.md", path.display()),
+            ScanWarning::UnreadableText(path, e) => {
+                write!(f, "{}: could not read entry: {}", path.display(), e)
+            }
+            ScanWarning::InvalidFrontMatter(path, e) => {
+                write!(f, "{}: invalid front matter: {}", path.display(), e)
+            }
+            ScanWarning::InvalidMediaInfo(path, e) => {
+                write!(f, "{}: invalid media metadata: {}", path.display(), e)
+            }
+            ScanWarning::Unreadable(path, e) => {
+                write!(f, "{}: could not read: {}", path.display(), e)
+            }
+        }
+    }
+}
+
+/// Returns the name of a directory entry, or records a warning if the name
+/// is not valid UTF-8 or the entry could not be read.
+fn read_name(
+    folder: &Path,
+    entry: std::io::Result<std::fs::DirEntry>,
+    warnings: &mut Vec<ScanWarning>,
+) -> Option<String> {
+    let entry = match entry {
+        Ok(entry) => entry,
+        Err(e) => {
+            warnings.push(ScanWarning::Unreadable(folder.to_owned(), e.to_string()));
+            return None;
+        }
+    };
+    match entry.file_name().into_string() {
+        Ok(name) => Some(name),
+        Err(_) => {
+            warnings.push(ScanWarning::NonUtf8Name(entry.path()));
+            None
+        }
+    }
+}
+
+fn get_all_files_in_folder(path: &Path, warnings: &mut Vec<ScanWarning>) -> Vec<PathBuf> {
     match std::fs::read_dir(path) {
-        Ok(entries) => entries
-            .filter_map(move |e| {
-                let fname = e.unwrap().file_name().to_str().unwrap().to_owned();
-                let fname = pathdiff::diff_paths(path.join(&fname), config::JOURNAL_PATH).unwrap();
-                Some(fname)
-            })
-            .collect(),
+        Ok(entries) => {
+            let mut files: Vec<PathBuf> = entries
+                .filter_map(|e| read_name(path, e, warnings))
+                .filter(|fname| !fname.starts_with('.'))
+                .filter_map(|fname| pathdiff::diff_paths(path.join(fname), config::JOURNAL_PATH))
+                .collect();
+            files.sort();
+            files
+        }
         Err(_) => vec![],
     }
 }
 
+fn read_notes(path: &Path, warnings: &mut Vec<ScanWarning>) -> Vec<Note> {
+    let Ok(read_dir) = std::fs::read_dir(path) else {
+        return vec![];
+    };
+    let mut notes: Vec<Note> = read_dir
+        .filter_map(|e| read_name(path, e, warnings))
+        .filter(|name| name.ends_with(".md") && name != "entry.md" && !name.starts_with('.'))
+        .map(|name| Note::new(path.join(&name), &name))
+        .collect();
+    notes.sort_by(|a, b| {
+        (a.time.is_none(), a.time, &a.path).cmp(&(b.time.is_none(), b.time, &b.path))
+    });
+    notes
+}
+
 impl Entry {
-    fn read(path: &Path) -> Self {
-        let pics = get_all_files_in_folder(&path.join("pics"));
-        let audio = get_all_files_in_folder(&path.join("audio"));
+    fn read(path: &Path, date: NaiveDate, warnings: &mut Vec<ScanWarning>) -> Self {
+        let pics = get_all_files_in_folder(&path.join("pics"), warnings);
+        let audio = get_all_files_in_folder(&path.join("audio"), warnings);
+        let notes = read_notes(path, warnings);
+        let text_path = path.join("entry.md");
+        let content = match std::fs::read_to_string(&text_path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                if notes.is_empty() {
+                    warnings.push(ScanWarning::MissingText(path.to_owned()));
+                }
+                String::new()
+            }
+            Err(e) => {
+                warnings.push(ScanWarning::UnreadableText(
+                    text_path.clone(),
+                    e.to_string(),
+                ));
+                String::new()
+            }
+        };
+        let front_matter = match front_matter::parse(&content) {
+            Ok((front_matter, _)) => front_matter,
+            Err(e) => {
+                warnings.push(ScanWarning::InvalidFrontMatter(
+                    text_path.clone(),
+                    e.to_string(),
+                ));
+                FrontMatter::default()
+            }
+        };
+        let media_infos = media_info::read(path).unwrap_or_else(|e| {
+            warnings.push(ScanWarning::InvalidMediaInfo(
+                path.join(media_info::FILE_NAME),
+                e.to_string(),
+            ));
+            MediaInfos::new()
+        });
+        let mut text = front_matter::split(&content).1.to_owned();
+        for note in notes.iter() {
+            match note.body() {
+                Ok(body) => {
+                    text.push('\n');
+                    text.push_str(&body);
+                }
+                Err(e) => warnings.push(ScanWarning::UnreadableText(
+                    note.path.clone(),
+                    e.to_string(),
+                )),
+            }
+        }
+        let mut tags: Vec<String> = vec![];
+        for tag in front_matter
+            .tags
+            .iter()
+            .map(|tag| tags::normalize(tag))
+            .chain(tags::find_hashtags(&text))
+        {
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
         Self {
-            text_path: (path.join("entry.md")).to_owned(),
+            date,
+            text_path,
+            notes,
+            front_matter,
+            tags,
+            links: links::find_links(&text),
+            text_length: text.chars().count(),
             pics,
             audio,
+            media_infos,
         }
     }
 
@@ -39,16 +265,48 @@ impl Entry {
         std::fs::read_to_string(&self.text_path)
     }
 
+    pub fn text_path(&self) -> &Path {
+        &self.text_path
+    }
+
+    /// The text of the entry without its front matter.
+    pub fn body(&self) -> Result<String> {
+        let content = self.content()?;
+        Ok(front_matter::split(&content).1.to_owned())
+    }
+
+    /// The text of `entry.md` and all notes of the day, without front matter.
+    pub fn full_text(&self) -> String {
+        std::iter::once(self.body())
+            .chain(self.notes.iter().map(|note| note.body()))
+            .filter_map(|text| text.ok())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn notes(&self) -> &[Note] {
+        self.notes.as_ref()
+    }
+
+    pub fn front_matter(&self) -> &FrontMatter {
+        &self.front_matter
+    }
+
+    /// All tags of the entry, from its front matter and inline `#hashtags`.
+    pub fn tags(&self) -> &[String] {
+        self.tags.as_ref()
+    }
+
     pub fn date_str(&self) -> String {
-        let filename = self
-            .text_path
-            .parent()
-            .unwrap()
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap();
-        filename.replace(".md", "")
+        self.date.format("%Y-%m-%d").to_string()
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    pub fn text_length(&self) -> usize {
+        self.text_length
     }
 
     pub fn pics(&self) -> &[PathBuf] {
@@ -58,25 +316,196 @@ impl Entry {
     pub fn audio(&self) -> &[PathBuf] {
         self.audio.as_ref()
     }
+
+    pub fn media_infos(&self) -> &MediaInfos {
+        &self.media_infos
+    }
+
+    /// The key of a picture or audio file of this entry in its sidecar
+    /// file, i.e. its path relative to the date folder.
+    pub fn media_key(&self, file: &Path) -> String {
+        let relative = file.strip_prefix(self.date_str()).unwrap_or(file);
+        relative.to_string_lossy().into_owned()
+    }
+
+    pub fn media_info(&self, file: &Path) -> MediaInfo {
+        self.media_infos
+            .get(&self.media_key(file))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn has_media(&self, key: &str) -> bool {
+        self.pics
+            .iter()
+            .chain(self.audio.iter())
+            .any(|file| self.media_key(file) == key)
+    }
 }
 
 #[derive(Debug)]
 pub struct Entries {
     entries: Vec<Entry>,
+    /// Maps the date of an entry to the dates of all entries linking to it.
+    backlinks: HashMap<String, Vec<String>>,
+    /// Maps each tag to the indices of all entries carrying it.
+    tags: BTreeMap<String, Vec<usize>>,
+    warnings: Vec<ScanWarning>,
+    provider_files: Vec<String>,
+}
+
+/// Files and folders in the journal root which are not entries but not
+/// worth a warning either, given the names of those that belong to providers.
+fn is_known_non_entry(path: &Path, name: &str, provider_files: &[String]) -> bool {
+    name.starts_with('.')
+        || provider_files.iter().any(|file| file == name)
+        || path == Path::new(config::DUMP_FOLDER_PATH)
 }
 
 impl Entries {
-    pub fn read(path: &Path) -> Result<Self> {
-        let path = path.to_owned();
-        let read_dir = std::fs::read_dir(path.clone())?;
-        let mut entries: Vec<_> = read_dir
-            .filter_map(move |e| {
-                let fname = e.unwrap().file_name().to_str().unwrap().to_owned();
-                Some(Entry::read(&path.join(fname)))
-            })
+    pub fn read(path: &Path, provider_files: Vec<String>) -> Result<Self> {
+        let read_dir = std::fs::read_dir(path)?;
+        let mut warnings = vec![];
+        let mut entries = vec![];
+        for e in read_dir {
+            let Some(name) = read_name(path, e, &mut warnings) else {
+                continue;
+            };
+            entries.extend(Self::read_folder(
+                path,
+                &name,
+                &provider_files,
+                &mut warnings,
+            ));
+        }
+        Ok(Self::new(entries, warnings, provider_files))
+    }
+
+    /// Returns a copy in which the folders of the journal at `path` with the
+    /// given `names` are read anew, such as folders that were added, changed
+    /// or removed. Everything else is kept as it is.
+    pub fn reread(&self, path: &Path, names: &[String]) -> Self {
+        let is_changed = |file: &Path| names.iter().any(|name| file.starts_with(path.join(name)));
+        let mut warnings: Vec<ScanWarning> = self
+            .warnings
+            .iter()
+            .filter(|warning| !is_changed(warning.path()))
+            .cloned()
+            .collect();
+        let mut entries: Vec<Entry> = self
+            .entries
+            .iter()
+            .filter(|entry| !names.contains(&entry.date_str()))
+            .cloned()
             .collect();
-        entries.sort_by_key(|entry| entry.date_str());
-        Ok(Self { entries })
+        for name in names {
+            if path.join(name).exists() {
+                entries.extend(Self::read_folder(
+                    path,
+                    name,
+                    &self.provider_files,
+                    &mut warnings,
+                ));
+            }
+        }
+        Self::new(entries, warnings, self.provider_files.clone())
+    }
+
+    /// Reads the entry in the folder `name` of the journal at `path`, or
+    /// records a warning if it is not an entry folder.
+    fn read_folder(
+        path: &Path,
+        name: &str,
+        provider_files: &[String],
+        warnings: &mut Vec<ScanWarning>,
+    ) -> Option<Entry> {
+        let entry_path = path.join(name);
+        if is_known_non_entry(&entry_path, name, provider_files) {
+            return None;
+        }
+        match NaiveDate::parse_from_str(name, "%Y-%m-%d") {
+            // Also rejects names like "2023-7-2", so that each date has exactly one folder name.
+            Ok(date) if entry_path.is_dir() && date.format("%Y-%m-%d").to_string() == name => {
+                Some(Entry::read(&entry_path, date, warnings))
+            }
+            _ => {
+                warnings.push(ScanWarning::NotAnEntry(entry_path));
+                None
+            }
+        }
+    }
+
+    fn new(
+        mut entries: Vec<Entry>,
+        mut warnings: Vec<ScanWarning>,
+        provider_files: Vec<String>,
+    ) -> Self {
+        entries.sort_by_key(|entry| entry.date);
+        warnings.sort_by_key(|warning| warning.to_string());
+        let backlinks = Self::find_backlinks(&entries);
+        let tags = Self::index_tags(&entries);
+        Self {
+            entries,
+            backlinks,
+            tags,
+            warnings,
+            provider_files,
+        }
+    }
+
+    pub fn warnings(&self) -> &[ScanWarning] {
+        &self.warnings
+    }
+
+    fn index_tags(entries: &[Entry]) -> BTreeMap<String, Vec<usize>> {
+        let mut tags: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            for tag in entry.tags() {
+                tags.entry(tag.clone()).or_default().push(i);
+            }
+        }
+        tags
+    }
+
+    /// Returns all tags in alphabetical order, together with the number of entries carrying them.
+    pub fn tags(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.tags
+            .iter()
+            .map(|(tag, entries)| (tag.as_str(), entries.len()))
+    }
+
+    pub fn get_by_tag(&self, tag: &str) -> Vec<&Entry> {
+        self.tags
+            .get(&tags::normalize(tag))
+            .map(|indices| indices.iter().map(|i| &self.entries[*i]).collect())
+            .unwrap_or_default()
+    }
+
+    fn find_backlinks(entries: &[Entry]) -> HashMap<String, Vec<String>> {
+        let mut backlinks: HashMap<String, Vec<String>> = HashMap::new();
+        for entry in entries {
+            let date = entry.date_str();
+            for target in entry.links.iter() {
+                if *target != date {
+                    backlinks
+                        .entry(target.clone())
+                        .or_default()
+                        .push(date.clone());
+                }
+            }
+        }
+        backlinks
+    }
+
+    pub fn backlinks(&self, entry: &Entry) -> &[String] {
+        self.backlinks
+            .get(&entry.date_str())
+            .map(|dates| dates.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter()
     }
 
     pub(crate) fn random(&self) -> Option<&Entry> {
@@ -84,8 +513,64 @@ impl Entries {
         self.entries.choose(&mut thread_rng)
     }
 
+    /// Returns all entries between `from` and `to`, both inclusive. Missing
+    /// bounds don't restrict the range.
+    pub fn range(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(move |entry| {
+            from.is_none_or(|from| entry.date >= from) && to.is_none_or(|to| entry.date <= to)
+        })
+    }
+
     pub fn get_by_date(&self, date: String) -> Option<&Entry> {
-        self.entries.iter().find(|entry| entry.date_str() == date)
+        let entry = self.get(NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok()?)?;
+        (entry.date_str() == date).then_some(entry)
+    }
+
+    pub fn get(&self, date: NaiveDate) -> Option<&Entry> {
+        self.entries
+            .binary_search_by_key(&date, |entry| entry.date)
+            .ok()
+            .map(|index| &self.entries[index])
+    }
+
+    /// Returns, for every year with entries, the entry closest to the given
+    /// month and day of that year, as long as it is at most `tolerance` days
+    /// away, together with that year. The newest year comes first. On
+    /// non-leap years, February 29th falls back to February 28th. The
+    /// tolerance is limited so that no entry is found for two years.
+    pub fn on_this_day(&self, month: u32, day: u32, tolerance: i64) -> Vec<(i32, &Entry)> {
+        let dated: Vec<(NaiveDate, &Entry)> = self
+            .entries
+            .iter()
+            .map(|entry| (entry.date(), entry))
+            .collect();
+        let (Some((first, _)), Some((last, _))) = (dated.first(), dated.last()) else {
+            return vec![];
+        };
+        let mut result = vec![];
+        for year in (first.year() - 1..=last.year() + 1).rev() {
+            let Some(target) = NaiveDate::from_ymd_opt(year, month, day)
+                .or_else(|| NaiveDate::from_ymd_opt(year, month, day - 1))
+            else {
+                continue;
+            };
+            let days = chrono::Days::new(tolerance.clamp(0, MAX_ON_THIS_DAY_TOLERANCE_DAYS) as u64);
+            let earliest = target.checked_sub_days(days).unwrap_or(NaiveDate::MIN);
+            let latest = target.checked_add_days(days).unwrap_or(NaiveDate::MAX);
+            let start = dated.partition_point(|(date, _)| *date < earliest);
+            let closest = dated[start..]
+                .iter()
+                .take_while(|(date, _)| *date <= latest)
+                .min_by_key(|(date, _)| (*date - target).num_days().abs());
+            if let Some((_, entry)) = closest {
+                result.push((year, *entry));
+            }
+        }
+        result
     }
 
     pub fn prev(&self, entry: &Entry) -> Option<&Entry> {
@@ -116,3 +601,203 @@ impl Entries {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_journal(name: &str, dates: &[&str]) -> PathBuf {
+        let journal =
+            std::env::temp_dir().join(format!("journal-{}-test-{}", name, std::process::id()));
+        for date in dates {
+            std::fs::create_dir_all(journal.join(date)).unwrap();
+            std::fs::write(journal.join(date).join("entry.md"), "text").unwrap();
+        }
+        journal
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn gets_entries_by_date() {
+        let journal = temp_journal("get", &["2023-07-03", "2023-07-01", "2024-01-01"]);
+        let entries = Entries::read(&journal, vec![]).unwrap();
+        std::fs::remove_dir_all(&journal).unwrap();
+        for day in ["2023-07-01", "2023-07-03", "2024-01-01"] {
+            assert_eq!(
+                entries.get(date(day)).map(|entry| entry.date_str()),
+                Some(day.to_owned())
+            );
+        }
+        assert!(entries.get(date("2023-07-02")).is_none());
+        assert!(entries.get(date("2025-01-01")).is_none());
+        assert!(entries.get_by_date("2023-07-03".to_owned()).is_some());
+        assert!(entries.get_by_date("2023-7-3".to_owned()).is_none());
+    }
+
+    fn on_this_day(dates: &[&str], month: u32, day: u32, tolerance: i64) -> Vec<(i32, String)> {
+        let journal = temp_journal(
+            &format!("on-this-day-{}-{}-{}", month, day, tolerance),
+            dates,
+        );
+        let entries = Entries::read(&journal, vec![]).unwrap();
+        std::fs::remove_dir_all(&journal).unwrap();
+        entries
+            .on_this_day(month, day, tolerance)
+            .into_iter()
+            .map(|(year, entry)| (year, entry.date_str()))
+            .collect()
+    }
+
+    #[test]
+    fn finds_the_closest_entry_of_each_year_within_the_tolerance() {
+        let dates = ["2021-07-05", "2022-07-01", "2022-07-03", "2023-07-02"];
+        assert_eq!(
+            on_this_day(&dates, 7, 2, 3),
+            vec![
+                (2023, "2023-07-02".to_owned()),
+                (2022, "2022-07-01".to_owned()),
+                (2021, "2021-07-05".to_owned()),
+            ]
+        );
+        assert_eq!(
+            on_this_day(&dates, 7, 2, 0),
+            vec![(2023, "2023-07-02".to_owned())]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_february_28th_on_non_leap_years() {
+        let dates = ["2023-02-28", "2024-02-28", "2024-02-29"];
+        assert_eq!(
+            on_this_day(&dates, 2, 29, 0),
+            vec![
+                (2024, "2024-02-29".to_owned()),
+                (2023, "2023-02-28".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_entries_across_the_turn_of_the_year() {
+        let dates = ["2022-12-30", "2024-01-02"];
+        assert_eq!(
+            on_this_day(&dates, 1, 1, 3),
+            vec![
+                (2024, "2024-01-02".to_owned()),
+                (2023, "2022-12-30".to_owned()),
+            ]
+        );
+        assert_eq!(
+            on_this_day(&dates, 12, 31, 3),
+            vec![
+                (2023, "2024-01-02".to_owned()),
+                (2022, "2022-12-30".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_no_entry_for_two_years_with_the_largest_tolerance() {
+        let dates = ["2022-07-02", "2023-07-02"];
+        for tolerance in [182, 183, i64::MAX] {
+            assert_eq!(
+                on_this_day(&dates, 1, 1, tolerance),
+                vec![
+                    (2023, "2023-07-02".to_owned()),
+                    (2022, "2022-07-02".to_owned()),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn ignores_the_files_of_providers_in_the_journal_root() {
+        let journal = temp_journal("provider-files", &["2023-07-01"]);
+        std::fs::write(journal.join("scrobbles.json"), "{}").unwrap();
+        let entries = Entries::read(&journal, vec!["scrobbles.json".to_owned()]).unwrap();
+        let without_providers = Entries::read(&journal, vec![]).unwrap();
+        std::fs::remove_dir_all(&journal).unwrap();
+        assert!(entries.warnings().is_empty());
+        assert_eq!(
+            without_providers
+                .warnings()
+                .iter()
+                .map(|warning| warning.path())
+                .collect::<Vec<_>>(),
+            vec![journal.join("scrobbles.json")]
+        );
+    }
+
+    #[test]
+    fn reads_only_date_folders_and_reports_everything_else() {
+        let journal = temp_journal("folders", &["2023-07-02", "2023-7-2", "notes"]);
+        std::fs::write(journal.join("2023-07-03"), "").unwrap();
+        std::fs::write(journal.join("todo.txt"), "").unwrap();
+        std::fs::write(journal.join(".hidden"), "").unwrap();
+        let entries = Entries::read(&journal, vec![]).unwrap();
+        std::fs::remove_dir_all(&journal).unwrap();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| entry.date_str())
+                .collect::<Vec<_>>(),
+            vec!["2023-07-02"]
+        );
+        let mut not_entries: Vec<_> = entries
+            .warnings()
+            .iter()
+            .filter(|warning| matches!(warning, ScanWarning::NotAnEntry(_)))
+            .map(|warning| warning.path().strip_prefix(&journal).unwrap().to_owned())
+            .collect();
+        not_entries.sort();
+        assert_eq!(
+            not_entries,
+            ["2023-07-03", "2023-7-2", "notes", "todo.txt"]
+                .map(PathBuf::from)
+                .to_vec()
+        );
+        assert_eq!(entries.warnings().len(), not_entries.len());
+    }
+
+    fn note(name: &str) -> (Option<String>, Option<String>) {
+        let note = Note::new(PathBuf::from(name), name);
+        (
+            note.time().map(|time| time.format("%H:%M").to_string()),
+            note.label().map(str::to_owned),
+        )
+    }
+
+    #[test]
+    fn reads_time_and_label_of_notes_from_their_name() {
+        let some = |s: &str| Some(s.to_owned());
+        assert_eq!(note("0830-morning.md"), (some("08:30"), some("morning")));
+        assert_eq!(
+            note("2145-late_night-walk.md"),
+            (some("21:45"), some("late night walk"))
+        );
+        assert_eq!(note("0830.md"), (some("08:30"), None));
+        assert_eq!(note("notes.md"), (None, some("notes")));
+        assert_eq!(note("2560-thoughts.md"), (None, some("2560 thoughts")));
+        assert_eq!(note("830-thoughts.md"), (None, some("830 thoughts")));
+    }
+
+    #[test]
+    fn sorts_timestamped_notes_first() {
+        let journal = temp_journal("notes", &["2023-07-02"]);
+        for name in ["notes.md", "1200-lunch.md", "0830.md", "entry.md"] {
+            std::fs::write(journal.join("2023-07-02").join(name), "").unwrap();
+        }
+        let notes = read_notes(&journal.join("2023-07-02"), &mut vec![]);
+        std::fs::remove_dir_all(&journal).unwrap();
+        assert_eq!(
+            notes
+                .iter()
+                .map(|note| note.path.file_name().unwrap().to_str().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["0830.md", "1200-lunch.md", "notes.md"]
+        );
+    }
+}
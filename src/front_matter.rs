@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+
+/// YAML front matter is enclosed in `---` lines, TOML front matter in `+++` lines.
+const YAML_DELIMITER: &str = "---";
+const TOML_DELIMITER: &str = "+++";
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    pub mood: Option<String>,
+    pub weather: Option<String>,
+    pub location: Option<String>,
+    pub tags: Vec<String>,
+    pub people: Vec<String>,
+}
+
+/// Splits `content` into the raw front matter block (including its
+/// delimiter lines) and the remaining body. The block is returned
+/// verbatim so that tools rewriting the body can put it back untouched.
+pub fn split(content: &str) -> (&str, &str) {
+    for delimiter in [YAML_DELIMITER, TOML_DELIMITER] {
+        if let Some(end) = find_block_end(content, delimiter) {
+            return content.split_at(end);
+        }
+    }
+    ("", content)
+}
+
+fn find_block_end(content: &str, delimiter: &str) -> Option<usize> {
+    let first_line = content.lines().next()?;
+    if first_line.trim_end() != delimiter {
+        return None;
+    }
+    let mut offset = first_line.len() + 1;
+    while offset < content.len() {
+        let line = content[offset..].lines().next()?;
+        offset += line.len();
+        if content[offset..].starts_with("\r\n") {
+            offset += 2;
+        } else if content[offset..].starts_with('\n') {
+            offset += 1;
+        }
+        if line.trim_end() == delimiter {
+            return Some(offset);
+        }
+    }
+    None
+}
+
+/// Parses the front matter at the start of `content` and returns it
+/// together with the body. Entries without front matter get the default.
+pub fn parse(content: &str) -> Result<(FrontMatter, &str), Box<dyn std::error::Error>> {
+    let (block, body) = split(content);
+    if block.is_empty() {
+        return Ok((FrontMatter::default(), body));
+    }
+    let delimiter = &block[..3];
+    let inner = block
+        .trim_end()
+        .trim_start_matches(delimiter)
+        .trim_end_matches(delimiter);
+    let front_matter = if delimiter == YAML_DELIMITER {
+        if inner.trim().is_empty() {
+            FrontMatter::default()
+        } else {
+            serde_yaml::from_str(inner)?
+        }
+    } else {
+        toml::from_str(inner)?
+    };
+    Ok((front_matter, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_yaml_and_toml_blocks_verbatim() {
+        assert_eq!(
+            split("---\ntitle: Lake\n---\nBody\n"),
+            ("---\ntitle: Lake\n---\n", "Body\n")
+        );
+        assert_eq!(
+            split("+++\r\ntitle = \"Lake\"\r\n+++\r\nBody"),
+            ("+++\r\ntitle = \"Lake\"\r\n+++\r\n", "Body")
+        );
+    }
+
+    #[test]
+    fn leaves_content_without_closed_block_untouched() {
+        assert_eq!(split("# Title\n---\n"), ("", "# Title\n---\n"));
+        assert_eq!(split("---\ntitle: Lake\n"), ("", "---\ntitle: Lake\n"));
+        assert_eq!(split(""), ("", ""));
+    }
+
+    #[test]
+    fn parses_yaml_front_matter() {
+        let (front_matter, body) =
+            parse("---\ntitle: Lake\ntags: [summer, swim]\npeople:\n  - Alex\n---\nBody").unwrap();
+        assert_eq!(
+            front_matter,
+            FrontMatter {
+                title: Some("Lake".to_owned()),
+                tags: vec!["summer".to_owned(), "swim".to_owned()],
+                people: vec!["Alex".to_owned()],
+                ..FrontMatter::default()
+            }
+        );
+        assert_eq!(body, "Body");
+    }
+
+    #[test]
+    fn parses_toml_front_matter() {
+        let (front_matter, body) =
+            parse("+++\nmood = \"calm\"\nweather = \"sunny\"\n+++\nBody").unwrap();
+        assert_eq!(front_matter.mood.as_deref(), Some("calm"));
+        assert_eq!(front_matter.weather.as_deref(), Some("sunny"));
+        assert_eq!(body, "Body");
+    }
+
+    #[test]
+    fn defaults_to_empty_front_matter() {
+        assert_eq!(parse("Body").unwrap(), (FrontMatter::default(), "Body"));
+        assert_eq!(
+            parse("---\n---\nBody").unwrap(),
+            (FrontMatter::default(), "Body")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_front_matter() {
+        assert!(parse("---\ntitle: [unclosed\n---\nBody").is_err());
+        assert!(parse("+++\ntitle = \n+++\nBody").is_err());
+    }
+}
@@ -1,21 +1,52 @@
-use chrono::{DateTime, NaiveDate, Utc};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, Timelike, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::hash::Hash;
+use std::ops::Range;
 use std::path::Path;
 
+use crate::config;
+use crate::editor;
+use crate::names::CanonicalNames;
+use crate::scrobble_import;
+
+/// How long a track is assumed to keep playing after it was scrobbled, since
+/// the exports do not contain track durations.
+const MAX_TRACK_MINUTES: i64 = 10;
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Scrobble {
     pub track: String,
     pub artist: String,
     pub album: String,
     pub date: i64, // Unix timestamp in milliseconds
+    /// Fields of the export that aren't used here, which are kept so that
+    /// saving the synced scrobbles doesn't lose them.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Scrobble {
+    /// The (UTC) day of the scrobble.
+    pub fn day(&self) -> Option<NaiveDate> {
+        DateTime::<Utc>::from_timestamp(self.date / 1000, 0).map(|dt| dt.naive_utc().date())
+    }
+
+    /// The time the scrobble started in the local time zone, which is what
+    /// photo capture times and note times are given in.
+    pub fn local_time(&self) -> Option<NaiveDateTime> {
+        DateTime::<Utc>::from_timestamp(self.date / 1000, 0)
+            .map(|dt| dt.with_timezone(&Local).naive_local())
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LastFmData {
     pub username: String,
     pub scrobbles: Vec<Scrobble>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone)]
@@ -32,83 +63,621 @@ pub struct TrackStats {
     pub play_count: usize,
 }
 
+#[derive(Debug, Clone)]
+pub struct ArtistStats {
+    pub name: String,
+    pub play_count: usize,
+}
+
+/// Counts the scrobbles per `key` and returns the `limit` most frequent
+/// keys, ties broken alphabetically.
+fn count_top<'a, K: Eq + Hash + Ord>(
+    scrobbles: impl IntoIterator<Item = &'a Scrobble>,
+    key: impl Fn(&Scrobble) -> K,
+    limit: usize,
+) -> Vec<(K, usize)> {
+    let mut counts: HashMap<K, usize> = HashMap::new();
+    for scrobble in scrobbles {
+        *counts.entry(key(scrobble)).or_insert(0) += 1;
+    }
+    let mut counts: Vec<(K, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.truncate(limit);
+    counts
+}
+
 pub struct LastFmAnalyzer {
+    /// The scrobbles are kept sorted by time.
     data: LastFmData,
+    /// Maps each day with scrobbles to the range of its scrobbles in `data`,
+    /// so that queries for days and ranges of days don't scan all scrobbles.
+    days: BTreeMap<NaiveDate, Range<usize>>,
+    first_days: HashMap<String, NaiveDate>,
 }
 
 impl LastFmAnalyzer {
+    fn from_data(mut data: LastFmData) -> Self {
+        data.scrobbles.sort_by_key(|scrobble| scrobble.date);
+        let mut analyzer = LastFmAnalyzer {
+            data,
+            days: BTreeMap::new(),
+            first_days: HashMap::new(),
+        };
+        analyzer.index_days();
+        analyzer
+    }
+
+    fn index_days(&mut self) {
+        self.days.clear();
+        self.first_days.clear();
+        for (i, scrobble) in self.data.scrobbles.iter().enumerate() {
+            let Some(day) = scrobble.day() else {
+                continue;
+            };
+            self.first_days
+                .entry(scrobble.artist.clone())
+                .or_insert(day);
+            self.days
+                .entry(day)
+                .and_modify(|range| range.end = i + 1)
+                .or_insert(i..i + 1);
+        }
+    }
+
+    pub fn from_scrobbles(username: &str, scrobbles: Vec<Scrobble>) -> Self {
+        Self::from_data(LastFmData {
+            username: username.to_owned(),
+            scrobbles,
+            extra: serde_json::Map::new(),
+        })
+    }
+
+    pub fn new(username: &str) -> Self {
+        Self::from_data(LastFmData {
+            username: username.to_owned(),
+            scrobbles: vec![],
+            extra: serde_json::Map::new(),
+        })
+    }
+
+    pub fn merged(analyzers: Vec<LastFmAnalyzer>) -> Self {
+        let mut usernames = vec![];
+        let mut scrobbles = vec![];
+        for analyzer in analyzers {
+            usernames.push(analyzer.data.username);
+            scrobbles.extend(analyzer.data.scrobbles);
+        }
+        Self::from_data(LastFmData {
+            username: usernames.join(" & "),
+            scrobbles,
+            extra: serde_json::Map::new(),
+        })
+    }
+
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
         let content = fs::read_to_string(path)?;
         let data: LastFmData = serde_json::from_str(&content)?;
-        Ok(LastFmAnalyzer { data })
+        Ok(Self::from_data(data))
     }
 
-    pub fn get_scrobbles_for_date(&self, date_str: &str) -> Vec<&Scrobble> {
-        // Parse the date string (assuming format like "2025-07-10")
-        let target_date = match NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
-            Ok(date) => date,
-            Err(_) => return Vec::new(),
-        };
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        editor::write_atomically(path.as_ref(), &serde_json::to_string(&self.data)?)?;
+        Ok(())
+    }
+
+    pub fn username(&self) -> &str {
+        &self.data.username
+    }
 
-        self.data
+    /// The timestamp of the most recent scrobble in milliseconds.
+    pub fn latest_scrobble(&self) -> Option<i64> {
+        self.data.scrobbles.last().map(|scrobble| scrobble.date)
+    }
+
+    /// Adds all `scrobbles` not already known, identified by their time,
+    /// artist and track. Returns the number of scrobbles added.
+    pub fn merge(&mut self, scrobbles: Vec<Scrobble>) -> usize {
+        let mut known: HashSet<(i64, String, String)> = self
+            .data
             .scrobbles
+            .iter()
+            .map(|s| (s.date, s.artist.clone(), s.track.clone()))
+            .collect();
+        let before = self.data.scrobbles.len();
+        for scrobble in scrobbles {
+            if known.insert((
+                scrobble.date,
+                scrobble.artist.clone(),
+                scrobble.track.clone(),
+            )) {
+                self.data.scrobbles.push(scrobble);
+            }
+        }
+        self.data.scrobbles.sort_by_key(|scrobble| scrobble.date);
+        self.index_days();
+        self.data.scrobbles.len() - before
+    }
+
+    /// The (UTC) day of the most recent scrobble.
+    pub fn latest_date(&self) -> Option<NaiveDate> {
+        self.days.keys().next_back().copied()
+    }
+
+    /// The (UTC) day of the first scrobble.
+    pub fn earliest_date(&self) -> Option<NaiveDate> {
+        self.days.keys().next().copied()
+    }
+
+    /// Returns all scrobbles from the start of `from` to the end of `to`, in
+    /// order of time.
+    pub fn get_scrobbles_in_range(&self, from: NaiveDate, to: NaiveDate) -> &[Scrobble] {
+        if from > to {
+            return &[];
+        }
+        let mut days = self.days.range(from..=to);
+        match (days.next(), days.next_back()) {
+            (Some((_, first)), Some((_, last))) => &self.data.scrobbles[first.start..last.end],
+            (Some((_, only)), None) => &self.data.scrobbles[only.clone()],
+            _ => &[],
+        }
+    }
+
+    pub fn get_scrobbles_for_date(&self, date_str: &str) -> &[Scrobble] {
+        match NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
+            Ok(date) => self.get_scrobbles_in_range(date, date),
+            Err(_) => &[],
+        }
+    }
+
+    /// Returns the scrobbles on `date` in the local time zone, in order of time.
+    pub fn get_scrobbles_for_local_date(&self, date: NaiveDate) -> Vec<&Scrobble> {
+        let (Some(from), Some(to)) = (date.pred_opt(), date.succ_opt()) else {
+            return vec![];
+        };
+        self.get_scrobbles_in_range(from, to)
             .iter()
             .filter(|scrobble| {
-                let scrobble_date = DateTime::<Utc>::from_timestamp(scrobble.date / 1000, 0)
-                    .map(|dt| dt.naive_utc().date())
-                    .unwrap_or_else(|| chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap());
-                scrobble_date == target_date
+                scrobble
+                    .local_time()
+                    .is_some_and(|time| time.date() == date)
             })
             .collect()
     }
 
-    pub fn get_top_albums_for_date(&self, date_str: &str, limit: usize) -> Vec<AlbumStats> {
-        let scrobbles = self.get_scrobbles_for_date(date_str);
-        let mut album_counts: HashMap<(String, String), usize> = HashMap::new();
+    /// Returns the track that was playing at the local `time`, i.e. the last
+    /// one scrobbled before it, unless that was too long ago.
+    pub fn playing_at(&self, time: NaiveDateTime) -> Option<&Scrobble> {
+        let date = time.date();
+        let (Some(from), Some(to)) = (date.pred_opt(), date.succ_opt()) else {
+            return None;
+        };
+        self.get_scrobbles_in_range(from, to)
+            .iter()
+            .filter_map(|scrobble| Some((scrobble, scrobble.local_time()?)))
+            .take_while(|(_, start)| *start <= time)
+            .last()
+            .filter(|(_, start)| (time - *start).num_minutes() < MAX_TRACK_MINUTES)
+            .map(|(scrobble, _)| scrobble)
+    }
 
-        for scrobble in scrobbles {
-            let key = (scrobble.album.clone(), scrobble.artist.clone());
-            *album_counts.entry(key).or_insert(0) += 1;
+    /// Returns the artists listened to on each of `dates` with any scrobbles,
+    /// in order of their first scrobble that day.
+    pub fn get_artists_by_date(&self, dates: &[NaiveDate]) -> HashMap<String, Vec<String>> {
+        let mut artists: HashMap<String, Vec<String>> = HashMap::new();
+        for (day, range) in dates
+            .iter()
+            .filter_map(|day| Some((day, self.days.get(day)?)))
+        {
+            let mut day_artists: Vec<String> = vec![];
+            for scrobble in &self.data.scrobbles[range.clone()] {
+                if !day_artists.contains(&scrobble.artist) {
+                    day_artists.push(scrobble.artist.clone());
+                }
+            }
+            artists.insert(day.format("%Y-%m-%d").to_string(), day_artists);
         }
+        artists
+    }
 
-        let mut albums: Vec<AlbumStats> = album_counts
-            .into_iter()
-            .map(|((name, artist), play_count)| AlbumStats {
-                name,
-                artist,
-                play_count,
-            })
-            .collect();
+    pub fn get_scrobble_counts_in_range(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> BTreeMap<NaiveDate, usize> {
+        if from > to {
+            return BTreeMap::new();
+        }
+        self.days
+            .range(from..=to)
+            .map(|(day, range)| (*day, range.len()))
+            .collect()
+    }
 
-        albums.sort_by(|a, b| b.play_count.cmp(&a.play_count));
-        albums.truncate(limit);
-        albums
+    pub fn get_top_albums_for_date(&self, date_str: &str, limit: usize) -> Vec<AlbumStats> {
+        match NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
+            Ok(date) => self.get_top_albums(date, date, limit),
+            Err(_) => vec![],
+        }
     }
 
     pub fn get_top_tracks_for_date(&self, date_str: &str, limit: usize) -> Vec<TrackStats> {
-        let scrobbles = self.get_scrobbles_for_date(date_str);
-        let mut track_counts: HashMap<(String, String), usize> = HashMap::new();
+        match NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
+            Ok(date) => self.get_top_tracks(date, date, limit),
+            Err(_) => vec![],
+        }
+    }
 
-        for scrobble in scrobbles {
-            let key = (scrobble.track.clone(), scrobble.artist.clone());
-            *track_counts.entry(key).or_insert(0) += 1;
+    pub fn get_top_albums(&self, from: NaiveDate, to: NaiveDate, limit: usize) -> Vec<AlbumStats> {
+        count_top(
+            self.get_scrobbles_in_range(from, to),
+            |scrobble| (scrobble.album.clone(), scrobble.artist.clone()),
+            limit,
+        )
+        .into_iter()
+        .map(|((name, artist), play_count)| AlbumStats {
+            name,
+            artist,
+            play_count,
+        })
+        .collect()
+    }
+
+    pub fn get_top_tracks(&self, from: NaiveDate, to: NaiveDate, limit: usize) -> Vec<TrackStats> {
+        count_top(
+            self.get_scrobbles_in_range(from, to),
+            |scrobble| (scrobble.track.clone(), scrobble.artist.clone()),
+            limit,
+        )
+        .into_iter()
+        .map(|((name, artist), play_count)| TrackStats {
+            name,
+            artist,
+            play_count,
+        })
+        .collect()
+    }
+
+    pub fn get_top_artists(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        limit: usize,
+    ) -> Vec<ArtistStats> {
+        count_top(
+            self.get_scrobbles_in_range(from, to),
+            |scrobble| scrobble.artist.clone(),
+            limit,
+        )
+        .into_iter()
+        .map(|(name, play_count)| ArtistStats { name, play_count })
+        .collect()
+    }
+
+    pub fn get_albums_of_artist(&self, artist: &str) -> Vec<AlbumStats> {
+        count_top(
+            self.data.scrobbles.iter().filter(|s| s.artist == artist),
+            |scrobble| (scrobble.album.clone(), scrobble.artist.clone()),
+            usize::MAX,
+        )
+        .into_iter()
+        .map(|((name, artist), play_count)| AlbumStats {
+            name,
+            artist,
+            play_count,
+        })
+        .collect()
+    }
+
+    /// Returns all tracks of an album (or of an artist, if `album` is `None`)
+    /// by number of plays.
+    pub fn get_tracks_of(&self, artist: &str, album: Option<&str>) -> Vec<TrackStats> {
+        count_top(
+            self.data
+                .scrobbles
+                .iter()
+                .filter(|s| s.artist == artist && album.is_none_or(|album| s.album == album)),
+            |scrobble| (scrobble.track.clone(), scrobble.artist.clone()),
+            usize::MAX,
+        )
+        .into_iter()
+        .map(|((name, artist), play_count)| TrackStats {
+            name,
+            artist,
+            play_count,
+        })
+        .collect()
+    }
+
+    /// Returns the number of plays of an album (or of an artist, if `album`
+    /// is `None`) on each (UTC) day it was listened to.
+    pub fn get_play_counts_of(
+        &self,
+        artist: &str,
+        album: Option<&str>,
+    ) -> BTreeMap<NaiveDate, usize> {
+        let mut counts = BTreeMap::new();
+        for (day, range) in &self.days {
+            let count = self.data.scrobbles[range.clone()]
+                .iter()
+                .filter(|s| s.artist == artist && album.is_none_or(|album| s.album == album))
+                .count();
+            if count > 0 {
+                counts.insert(*day, count);
+            }
         }
+        counts
+    }
 
-        let mut tracks: Vec<TrackStats> = track_counts
-            .into_iter()
-            .map(|((name, artist), play_count)| TrackStats {
-                name,
-                artist,
-                play_count,
-            })
+    /// Returns the number of scrobbles in each hour of the day (in local
+    /// time, like the listening timeline of a day), as a measure of when
+    /// music is listened to.
+    pub fn get_scrobbles_per_hour(&self, from: NaiveDate, to: NaiveDate) -> [usize; 24] {
+        let mut hours = [0; 24];
+        for scrobble in self.get_scrobbles_in_range(from, to) {
+            if let Some(time) = scrobble.local_time() {
+                hours[time.hour() as usize] += 1;
+            }
+        }
+        hours
+    }
+
+    /// Returns the artists whose first scrobble ever falls between `from`
+    /// and `to`, together with the day of that scrobble, in order of discovery.
+    pub fn get_new_artists(&self, from: NaiveDate, to: NaiveDate) -> Vec<(String, NaiveDate)> {
+        let mut new_artists: Vec<(String, NaiveDate)> = self
+            .first_days
+            .iter()
+            .filter(|(_, day)| **day >= from && **day <= to)
+            .map(|(artist, day)| (artist.clone(), *day))
             .collect();
+        new_artists.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        new_artists
+    }
 
-        tracks.sort_by(|a, b| b.play_count.cmp(&a.play_count));
-        tracks.truncate(limit);
-        tracks
+    /// Returns the day between `from` and `to` on which the album was
+    /// played most, together with the number of plays that day.
+    pub fn get_top_day_for_album(
+        &self,
+        artist: &str,
+        album: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Option<(NaiveDate, usize)> {
+        if from > to {
+            return None;
+        }
+        self.days
+            .range(from..=to)
+            .map(|(day, range)| {
+                let plays = self.data.scrobbles[range.clone()]
+                    .iter()
+                    .filter(|scrobble| scrobble.artist == artist && scrobble.album == album)
+                    .count();
+                (*day, plays)
+            })
+            .filter(|(_, plays)| *plays > 0)
+            .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
     }
 
     pub fn get_total_scrobbles_for_date(&self, date_str: &str) -> usize {
         self.get_scrobbles_for_date(date_str).len()
     }
 }
+
+/// The scrobbles of all configured accounts together with any problems
+/// found while loading them, which are shown in the UI.
+#[derive(Default)]
+pub struct LastFmSources {
+    /// One analyzer per account, or a single one for all accounts if
+    /// `LASTFM_MERGE_ACCOUNTS` is set.
+    pub analyzers: Vec<LastFmAnalyzer>,
+    pub status: Vec<String>,
+}
+
+impl LastFmSources {
+    pub fn load(journal: &Path) -> Self {
+        let mut analyzers = vec![];
+        let mut status = vec![];
+        let today = Utc::now().date_naive();
+        let mut imported = vec![];
+        for account in config::LASTFM_ACCOUNTS {
+            let path = journal.join(account.file);
+            match scrobble_import::import(&path) {
+                Ok(scrobbles) => imported.push((account, scrobbles)),
+                Err(e) => status.push(format!(
+                    "No music data for {}: could not read {}: {}",
+                    account.username,
+                    path.display(),
+                    e
+                )),
+            }
+        }
+        // Names are normalized across all accounts, so that merged accounts
+        // agree on the spelling.
+        let names = CanonicalNames::new(imported.iter().flat_map(|(_, scrobbles)| scrobbles));
+        for (account, mut scrobbles) in imported {
+            scrobbles
+                .iter_mut()
+                .for_each(|scrobble| names.apply(scrobble));
+            let analyzer = LastFmAnalyzer::from_scrobbles(account.username, scrobbles);
+            match analyzer.latest_date() {
+                None => status.push(format!(
+                    "The music data of {} contains no scrobbles",
+                    account.username
+                )),
+                Some(latest) if (today - latest).num_days() > config::LASTFM_STALE_DAYS => status
+                    .push(format!(
+                        "The music data of {} ends on {}. {}",
+                        account.username,
+                        latest.format("%Y-%m-%d"),
+                        if config::LASTFM_API_KEY.is_some() {
+                            "Syncing new scrobbles may be failing."
+                        } else {
+                            "Import a new export or configure an API key to sync automatically."
+                        }
+                    )),
+                Some(_) => {}
+            }
+            analyzers.push(analyzer);
+        }
+        if config::LASTFM_MERGE_ACCOUNTS && analyzers.len() > 1 {
+            analyzers = vec![LastFmAnalyzer::merged(analyzers)];
+        }
+        Self { analyzers, status }
+    }
+
+    pub fn playing_at(&self, time: NaiveDateTime) -> Option<&Scrobble> {
+        self.analyzers
+            .iter()
+            .find_map(|analyzer| analyzer.playing_at(time))
+    }
+
+    pub fn get_artists_by_date(&self, dates: &[NaiveDate]) -> HashMap<String, Vec<String>> {
+        let mut artists: HashMap<String, Vec<String>> = HashMap::new();
+        for analyzer in &self.analyzers {
+            for (date, day) in analyzer.get_artists_by_date(dates) {
+                let merged = artists.entry(date).or_default();
+                for artist in day {
+                    if !merged.contains(&artist) {
+                        merged.push(artist);
+                    }
+                }
+            }
+        }
+        artists
+    }
+
+    pub fn get_scrobble_counts_in_range(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> BTreeMap<NaiveDate, usize> {
+        let mut counts: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+        for analyzer in &self.analyzers {
+            for (date, count) in analyzer.get_scrobble_counts_in_range(from, to) {
+                *counts.entry(date).or_default() += count;
+            }
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scrobble(track: &str, time: &str) -> Scrobble {
+        let time = NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S").unwrap();
+        Scrobble {
+            track: track.to_owned(),
+            artist: "Artist".to_owned(),
+            album: "Album".to_owned(),
+            date: time.and_utc().timestamp_millis(),
+            extra: serde_json::Map::new(),
+        }
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    fn tracks(scrobbles: &[Scrobble]) -> Vec<&str> {
+        scrobbles.iter().map(|s| s.track.as_str()).collect()
+    }
+
+    fn analyzer() -> LastFmAnalyzer {
+        LastFmAnalyzer::from_scrobbles(
+            "user",
+            vec![
+                scrobble("d", "2023-07-03 00:00:00"),
+                scrobble("b", "2023-07-02 00:00:00"),
+                scrobble("a", "2023-07-01 23:59:59"),
+                scrobble("c", "2023-07-02 23:59:59"),
+                scrobble("e", "2023-07-05 12:00:00"),
+            ],
+        )
+    }
+
+    #[test]
+    fn range_includes_whole_first_and_last_day() {
+        let analyzer = analyzer();
+        assert_eq!(
+            tracks(analyzer.get_scrobbles_in_range(date("2023-07-02"), date("2023-07-02"))),
+            vec!["b", "c"]
+        );
+        assert_eq!(
+            tracks(analyzer.get_scrobbles_in_range(date("2023-07-01"), date("2023-07-03"))),
+            vec!["a", "b", "c", "d"]
+        );
+        assert_eq!(
+            tracks(analyzer.get_scrobbles_for_date("2023-07-01")),
+            vec!["a"]
+        );
+    }
+
+    #[test]
+    fn range_starts_and_ends_at_days_with_scrobbles() {
+        let analyzer = analyzer();
+        assert_eq!(
+            tracks(analyzer.get_scrobbles_in_range(date("2023-07-03"), date("2023-07-10"))),
+            vec!["d", "e"]
+        );
+        assert_eq!(
+            tracks(analyzer.get_scrobbles_in_range(date("2023-06-01"), date("2023-07-01"))),
+            vec!["a"]
+        );
+        assert!(analyzer
+            .get_scrobbles_in_range(date("2023-07-04"), date("2023-07-04"))
+            .is_empty());
+        assert!(analyzer
+            .get_scrobbles_in_range(date("2023-07-03"), date("2023-07-01"))
+            .is_empty());
+    }
+
+    #[test]
+    fn merge_keeps_day_index_up_to_date() {
+        let mut analyzer = analyzer();
+        let added = analyzer.merge(vec![
+            scrobble("a", "2023-07-01 23:59:59"),
+            scrobble("f", "2023-07-04 00:00:00"),
+        ]);
+        assert_eq!(added, 1);
+        assert_eq!(
+            tracks(analyzer.get_scrobbles_in_range(date("2023-07-03"), date("2023-07-04"))),
+            vec!["d", "f"]
+        );
+        assert_eq!(analyzer.earliest_date(), Some(date("2023-07-01")));
+        assert_eq!(analyzer.latest_date(), Some(date("2023-07-05")));
+    }
+
+    #[test]
+    fn counts_scrobbles_per_local_hour() {
+        let analyzer = analyzer();
+        let hours = analyzer.get_scrobbles_per_hour(date("2023-07-05"), date("2023-07-05"));
+        let scrobble = &analyzer.get_scrobbles_in_range(date("2023-07-05"), date("2023-07-05"))[0];
+        let local_hour = scrobble.local_time().unwrap().hour() as usize;
+        assert_eq!(hours[local_hour], 1);
+        assert_eq!(hours.iter().sum::<usize>(), 1);
+    }
+
+    #[test]
+    fn keeps_unknown_fields_of_the_export_when_saving() {
+        let path =
+            std::env::temp_dir().join(format!("journal-lastfm-test-{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{"username": "user", "exported": 1, "scrobbles": [{"track": "a", "artist": "Artist", "album": "Album", "date": 1000, "albumId": "x"}]}"#,
+        )
+        .unwrap();
+        let mut analyzer = LastFmAnalyzer::load_from_file(&path).unwrap();
+        analyzer.merge(vec![scrobble("b", "2023-07-01 12:00:00")]);
+        analyzer.save_to_file(&path).unwrap();
+        let saved: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(saved["exported"], 1);
+        assert_eq!(saved["scrobbles"][0]["albumId"], "x");
+        assert_eq!(saved["scrobbles"][1]["track"], "b");
+        assert!(saved["scrobbles"][1].get("albumId").is_none());
+    }
+}
@@ -0,0 +1,801 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+use rouille::percent_encoding::percent_decode;
+use rouille::{Request, Response};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::album_art::AlbumArt;
+use crate::calendar;
+use crate::config;
+use crate::day_data::{DayDataProvider, Site};
+use crate::entries::{Entries, Entry};
+use crate::lastfm::{LastFmAnalyzer, LastFmSources};
+use crate::lastfm_sync;
+use crate::web::{escape_html, parse_date_param, url_segment};
+
+/// The music listened to each day, from the scrobbles of the configured
+/// Last.fm accounts, together with the music pages and album art.
+#[derive(Default)]
+pub struct LastFmProvider {
+    sources: RwLock<Arc<LastFmSources>>,
+    /// Empty until the music folders have been scanned in the background,
+    /// which starts once album art is first needed.
+    album_art: Arc<RwLock<Arc<AlbumArt>>>,
+    album_art_scan: OnceLock<()>,
+}
+
+impl DayDataProvider for LastFmProvider {
+    fn name(&self) -> &'static str {
+        "Last.fm"
+    }
+
+    fn load(&self) {
+        let sources = LastFmSources::load(Path::new(config::JOURNAL_PATH));
+        *self.sources.write().unwrap() = Arc::new(sources);
+    }
+
+    fn status(&self) -> Vec<String> {
+        self.sources().status.clone()
+    }
+
+    fn journal_files(&self) -> Vec<String> {
+        config::LASTFM_ACCOUNTS
+            .iter()
+            .map(|account| account.file.to_owned())
+            .collect()
+    }
+
+    fn fetch_interval(&self) -> Option<std::time::Duration> {
+        config::LASTFM_API_KEY.map(|_| config::LASTFM_SYNC_INTERVAL)
+    }
+
+    /// Fetches new scrobbles of all accounts into their stores. Accounts
+    /// failing to sync don't keep the others from syncing.
+    fn fetch(&self) -> Result<usize, String> {
+        let Some(api_key) = config::LASTFM_API_KEY else {
+            return Ok(0);
+        };
+        let client = lastfm_sync::Client::new(config::LASTFM_API_URL, api_key);
+        let mut added = 0;
+        let mut errors = vec![];
+        for account in config::LASTFM_ACCOUNTS {
+            let path = Path::new(config::JOURNAL_PATH).join(account.file);
+            match lastfm_sync::sync(&path, account.username, &client) {
+                Ok(count) => added += count,
+                Err(e) => errors.push(format!("{}: {}", account.username, e)),
+            }
+        }
+        if errors.is_empty() {
+            Ok(added)
+        } else {
+            Err(errors.join(", "))
+        }
+    }
+
+    fn calendar_label(&self) -> (&'static str, &'static str) {
+        ("🎵", "tracks played")
+    }
+
+    fn counts(&self, from: NaiveDate, to: NaiveDate) -> BTreeMap<NaiveDate, usize> {
+        self.sources().get_scrobble_counts_in_range(from, to)
+    }
+
+    /// The artists listened to on each day.
+    fn search_text(&self, dates: &[NaiveDate]) -> HashMap<String, Vec<String>> {
+        self.sources().get_artists_by_date(dates)
+    }
+
+    fn section_html(
+        &self,
+        entry: &Entry,
+        capture_times: &HashMap<PathBuf, NaiveDateTime>,
+    ) -> Option<String> {
+        self.lastfm_html(entry, capture_times)
+    }
+
+    /// The track that was playing when the picture was taken.
+    fn photo_annotation(&self, time: NaiveDateTime) -> Option<String> {
+        let sources = self.sources();
+        let scrobble = sources.playing_at(time)?;
+        Some(format!(
+            "♪ {} – {}",
+            escape_html(&scrobble.artist),
+            escape_html(&scrobble.track)
+        ))
+    }
+
+    fn response(&self, request: &Request, site: &Site) -> Option<Response> {
+        if request.method() != "GET" {
+            return None;
+        }
+        let url = request.raw_url();
+        let path = url.split('?').next().unwrap_or_default();
+        let segments: Vec<String> = path
+            .split('/')
+            .skip(1)
+            .map(|segment| {
+                percent_decode(segment.as_bytes())
+                    .decode_utf8_lossy()
+                    .into_owned()
+            })
+            .collect();
+        let segments: Vec<&str> = segments.iter().map(|segment| segment.as_str()).collect();
+        match segments.as_slice() {
+            ["music"] => Some(self.music(
+                site,
+                request.get_param("period"),
+                request.get_param("from"),
+                request.get_param("to"),
+            )),
+            ["music", "artist", name] => Some(self.music_item(site, name, None)),
+            ["music", "album", artist, name] => Some(self.music_item(site, artist, Some(name))),
+            ["album-art"] => Some(self.album_art_response(
+                &request.get_param("artist").unwrap_or_default(),
+                &request.get_param("album").unwrap_or_default(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl LastFmProvider {
+    fn sources(&self) -> Arc<LastFmSources> {
+        self.sources.read().unwrap().clone()
+    }
+
+    fn album_art(&self) -> Arc<AlbumArt> {
+        self.album_art_scan.get_or_init(|| {
+            let album_art = self.album_art.clone();
+            std::thread::spawn(move || {
+                let folders: Vec<PathBuf> = std::iter::once(config::JOURNAL_PATH)
+                    .chain(config::MUSIC_LIBRARY_PATH)
+                    .map(PathBuf::from)
+                    .collect();
+                let scanned = AlbumArt::scan(&folders, config::ALBUM_ART_CACHE_PATH.into());
+                *album_art.write().unwrap() = Arc::new(scanned);
+            });
+        });
+        self.album_art.read().unwrap().clone()
+    }
+
+    /// Serves the thumbnail of the cover of an album.
+    fn album_art_response(&self, artist: &str, album: &str) -> Response {
+        match self
+            .album_art()
+            .thumbnail(artist, album)
+            .and_then(|path| std::fs::File::open(path).ok())
+        {
+            Some(file) => Response::from_file("image/jpeg", file),
+            None => Response::empty_404(),
+        }
+    }
+
+    /// The cover of an album, if there is one in the local music files.
+    fn album_art_html(&self, artist: &str, album: &str) -> String {
+        if !self.album_art().contains(artist, album) {
+            return String::new();
+        }
+        format!(
+            r#"<img class="album-art" src="/album-art?artist={}&album={}" alt="" loading="lazy" />"#,
+            url_segment(artist),
+            url_segment(album)
+        )
+    }
+
+    /// Renders the music section of an entry, with one part per Last.fm
+    /// account unless the accounts are merged.
+    fn lastfm_html(
+        &self,
+        entry: &Entry,
+        capture_times: &HashMap<PathBuf, NaiveDateTime>,
+    ) -> Option<String> {
+        let lastfm = self.sources();
+        let show_username = lastfm.analyzers.len() > 1;
+        let accounts: Vec<String> = lastfm
+            .analyzers
+            .iter()
+            .enumerate()
+            .filter_map(|(i, analyzer)| {
+                self.lastfm_account_html(entry, analyzer, i, show_username, capture_times)
+            })
+            .collect();
+        if accounts.is_empty() {
+            return None;
+        }
+        Some(format!(
+            r#"<div class="lastfm-section">{}</div>"#,
+            accounts.join("\n")
+        ))
+    }
+
+    fn lastfm_account_html(
+        &self,
+        entry: &Entry,
+        analyzer: &LastFmAnalyzer,
+        index: usize,
+        show_username: bool,
+        capture_times: &HashMap<PathBuf, NaiveDateTime>,
+    ) -> Option<String> {
+        let date_str = &entry.date_str();
+        let total_scrobbles = analyzer.get_total_scrobbles_for_date(date_str);
+        let heading = if show_username {
+            format!(
+                "Music on {} · {}",
+                date_str,
+                escape_html(analyzer.username())
+            )
+        } else {
+            format!("Music on {}", date_str)
+        };
+
+        if total_scrobbles == 0 {
+            // Point out that the data ends before this day, rather than
+            // suggesting that no music was played.
+            let latest = analyzer.latest_date()?;
+            if latest >= entry.date() {
+                return None;
+            }
+            return Some(format!(
+                r#"<div class="lastfm-account"><h3>{}</h3><p class="total-tracks">No Last.fm data after {}</p></div>"#,
+                heading,
+                latest.format("%Y-%m-%d")
+            ));
+        }
+
+        let top_tracks = analyzer.get_top_tracks_for_date(date_str, 5);
+        let top_albums = analyzer.get_top_albums_for_date(date_str, 5);
+
+        let mut html = format!(
+            r#"<div class="lastfm-account">
+                <h3>{}</h3>
+                <p class="total-tracks">{} tracks played</p>
+                
+                <div class="tabs">
+                    <button class="tab-btn active" onclick="switchTab(event, 'albums-{}')">Top Albums</button>
+                    <button class="tab-btn" onclick="switchTab(event, 'tracks-{}')">Top Tracks</button>
+                    <button class="tab-btn" onclick="switchTab(event, 'timeline-{}')">Timeline</button>
+                </div>
+                
+                <div id="albums-{}" class="tab-content active">"#,
+            heading, total_scrobbles, index, index, index, index
+        );
+
+        // Albums tab
+        if !top_albums.is_empty() {
+            html.push_str(r#"<ul class="stats-list">"#);
+            for album in &top_albums {
+                html.push_str(&format!(
+                    r#"<li>{}<span class="item-name">{}</span><br><span class="artist-name">{}</span> <span class="play-count">({} plays)</span></li>"#,
+                    self.album_art_html(&album.artist, &album.name),
+                    album_link(&album.artist, &album.name),
+                    artist_link(&album.artist),
+                    album.play_count
+                ));
+            }
+            html.push_str("</ul>");
+        }
+        html.push_str("</div>");
+
+        // Tracks tab
+        html.push_str(&format!(
+            r#"<div id="tracks-{}" class="tab-content">"#,
+            index
+        ));
+        if !top_tracks.is_empty() {
+            html.push_str(r#"<ul class="stats-list">"#);
+            for track in &top_tracks {
+                html.push_str(&format!(
+                    r#"<li><span class="item-name">{}</span><br><span class="artist-name">{}</span> <span class="play-count">({} plays)</span></li>"#,
+                    escape_html(&track.name),
+                    artist_link(&track.artist),
+                    track.play_count
+                ));
+            }
+            html.push_str("</ul>");
+        }
+        html.push_str("</div>");
+
+        // Timeline tab
+        html.push_str(&format!(
+            r#"<div id="timeline-{}" class="tab-content">{}</div>"#,
+            index,
+            self.listening_timeline_html(entry, analyzer, capture_times)
+        ));
+
+        html.push_str("</div>");
+        Some(html)
+    }
+
+    /// Lists the tracks played on the day of an entry hour by hour, together
+    /// with the pictures taken in each hour.
+    fn listening_timeline_html(
+        &self,
+        entry: &Entry,
+        analyzer: &LastFmAnalyzer,
+        capture_times: &HashMap<PathBuf, NaiveDateTime>,
+    ) -> String {
+        let mut events: Vec<(NaiveDateTime, String)> = analyzer
+            .get_scrobbles_for_local_date(entry.date())
+            .into_iter()
+            .filter_map(|scrobble| {
+                let time = scrobble.local_time()?;
+                Some((
+                    time,
+                    format!(
+                        r#"<li><span class="listening-time">{}</span> <span class="item-name">{}</span><span class="artist-name">{}</span></li>"#,
+                        time.format("%H:%M"),
+                        escape_html(&scrobble.track),
+                        escape_html(&scrobble.artist)
+                    ),
+                ))
+            })
+            .collect();
+        for (pic, time) in capture_times {
+            let pic = escape_html(&pic.to_string_lossy());
+            events.push((
+                *time,
+                format!(
+                    r#"<li class="listening-photo"><span class="listening-time">{}</span> <img src="/{}" alt="Photo taken at {}" loading="lazy" /></li>"#,
+                    time.format("%H:%M"),
+                    pic,
+                    time.format("%H:%M")
+                ),
+            ));
+        }
+        events.sort_by_key(|(time, _)| *time);
+        let (Some((first, _)), Some((last, _))) = (events.first(), events.last()) else {
+            return r#"<p class="total-tracks">No tracks played on this day in local time</p>"#
+                .to_owned();
+        };
+        let mut html = String::new();
+        for hour in first.hour()..=last.hour() {
+            let items: Vec<&str> = events
+                .iter()
+                .filter(|(time, _)| time.hour() == hour)
+                .map(|(_, item)| item.as_str())
+                .collect();
+            html.push_str(&format!(
+                r#"<div class="listening-hour{}"><div class="listening-hour-label">{:02}:00</div><ul class="stats-list">{}</ul></div>"#,
+                if items.is_empty() { " empty" } else { "" },
+                hour,
+                items.join("")
+            ));
+        }
+        html
+    }
+
+    /// Shows listening statistics between `from` and `to`, or otherwise for
+    /// the week, month or year up to today or for all time.
+    fn music(
+        &self,
+        site: &Site,
+        period: Option<String>,
+        from: Option<String>,
+        to: Option<String>,
+    ) -> Response {
+        let today = chrono::Local::now().date_naive();
+        let (from, to) = (parse_date_param(from), parse_date_param(to));
+        let period = if from.is_some() || to.is_some() {
+            "custom".to_owned()
+        } else {
+            period.unwrap_or_else(|| "year".to_owned())
+        };
+        let (from, to) = match period.as_str() {
+            "custom" => (from, to),
+            "week" => (Some(today - chrono::Days::new(6)), Some(today)),
+            "month" => (today.with_day(1), Some(today)),
+            "all" => (None, None),
+            _ => (today.with_ordinal(1), Some(today)),
+        };
+        let lastfm = self.sources();
+        let show_username = lastfm.analyzers.len() > 1;
+        let mut sections: Vec<String> = lastfm
+            .status
+            .iter()
+            .map(|status| {
+                format!(
+                    r#"<div class="music-section total-tracks">{}</div>"#,
+                    escape_html(status)
+                )
+            })
+            .collect();
+        sections.extend(
+            lastfm
+                .analyzers
+                .iter()
+                .map(|analyzer| self.music_section_html(site, analyzer, from, to, show_username)),
+        );
+        let format_date = |date: Option<NaiveDate>| {
+            date.map(|date| date.format("%Y-%m-%d").to_string())
+                .unwrap_or_default()
+        };
+        let title = match (from, to) {
+            (None, None) => "All time".to_owned(),
+            (from, to) => format!("{} – {}", format_date(from), format_date(to)),
+        };
+        let mut context = tera::Context::new();
+        context.insert("title", &title);
+        context.insert("period", &period);
+        context.insert("from", &format_date(from));
+        context.insert("to", &format_date(to));
+        context.insert("sections", &sections.join("\n"));
+        Response::html(site.tera.render("music.html", &context).unwrap())
+    }
+
+    fn music_section_html(
+        &self,
+        site: &Site,
+        analyzer: &LastFmAnalyzer,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        show_username: bool,
+    ) -> String {
+        let heading = if show_username {
+            format!("<h2>{}</h2>", escape_html(analyzer.username()))
+        } else {
+            String::new()
+        };
+        let (Some(earliest), Some(latest)) = (analyzer.earliest_date(), analyzer.latest_date())
+        else {
+            return format!(
+                r#"<div class="music-section">{}<p class="total-tracks">No scrobbles</p></div>"#,
+                heading
+            );
+        };
+        // Limiting the range to the span of the data keeps the months table
+        // from going through all the years of a far-reaching custom range.
+        let from = from.map_or(earliest, |from| from.max(earliest));
+        let to = to.map_or(latest, |to| to.min(latest));
+        let total = analyzer.get_scrobbles_in_range(from, to).len();
+        let mut html = format!(
+            r#"<div class="music-section">{}<p class="total-tracks">{} tracks played</p>"#,
+            heading, total
+        );
+        if total == 0 {
+            html.push_str("</div>");
+            return html;
+        }
+
+        let list =
+            |items: Vec<String>| format!(r#"<ul class="stats-list">{}</ul>"#, items.join(""));
+        let artists = analyzer
+            .get_top_artists(from, to, 10)
+            .into_iter()
+            .map(|artist| {
+                format!(
+                    r#"<li><span class="item-name">{}</span> <span class="play-count">({} plays)</span></li>"#,
+                    artist_link(&artist.name),
+                    artist.play_count
+                )
+            })
+            .collect();
+        let albums = analyzer
+            .get_top_albums(from, to, 10)
+            .into_iter()
+            .map(|album| {
+                let top_day = analyzer
+                    .get_top_day_for_album(&album.artist, &album.name, from, to)
+                    .map(|(day, _)| format!(" · most on {}", date_link(site.entries, day)))
+                    .unwrap_or_default();
+                format!(
+                    r#"<li>{}<span class="item-name">{}</span><br><span class="artist-name">{}</span> <span class="play-count">({} plays{})</span></li>"#,
+                    self.album_art_html(&album.artist, &album.name),
+                    album_link(&album.artist, &album.name),
+                    artist_link(&album.artist),
+                    album.play_count,
+                    top_day
+                )
+            })
+            .collect();
+        let tracks = analyzer
+            .get_top_tracks(from, to, 10)
+            .into_iter()
+            .map(|track| {
+                format!(
+                    r#"<li><span class="item-name">{}</span><br><span class="artist-name">{}</span> <span class="play-count">({} plays)</span></li>"#,
+                    escape_html(&track.name),
+                    artist_link(&track.artist),
+                    track.play_count
+                )
+            })
+            .collect();
+        html.push_str(&format!(
+            r#"<div class="stats-columns"><div><h3>Top Artists</h3>{}</div><div><h3>Top Albums</h3>{}</div><div><h3>Top Tracks</h3>{}</div></div>"#,
+            list(artists),
+            list(albums),
+            list(tracks)
+        ));
+
+        let hours = analyzer.get_scrobbles_per_hour(from, to);
+        let max = hours.iter().copied().max().unwrap_or(0).max(1);
+        html.push_str(r#"<h3>Listening by hour of day</h3><div class="hours">"#);
+        for (hour, count) in hours.iter().enumerate() {
+            html.push_str(&format!(
+                r#"<div class="hour" style="height: {}%" title="{:02}:00: {} tracks"></div>"#,
+                count * 100 / max,
+                hour,
+                count
+            ));
+        }
+        html.push_str(
+            r#"</div><div class="hour-labels"><span>0:00</span><span>6:00</span><span>12:00</span><span>18:00</span><span>24:00</span></div>"#,
+        );
+
+        let new_artists = analyzer.get_new_artists(from, to);
+        if !new_artists.is_empty() {
+            const SHOWN_NEW_ARTISTS: usize = 20;
+            html.push_str(&format!("<h3>{} new artists</h3>", new_artists.len()));
+            html.push_str(&list(
+                new_artists
+                    .iter()
+                    .take(SHOWN_NEW_ARTISTS)
+                    .map(|(artist, day)| {
+                        format!(
+                            r#"<li><span class="item-name">{}</span> <span class="play-count">first played {}</span></li>"#,
+                            artist_link(artist),
+                            date_link(site.entries, *day)
+                        )
+                    })
+                    .collect(),
+            ));
+        }
+
+        let months = self.music_months_html(site, analyzer, from, to);
+        if !months.is_empty() {
+            html.push_str(&format!(
+                "<h3>By month</h3><table><tr><th>Month</th><th>Tracks</th><th>Top artist</th><th>Top album</th></tr>{}</table>",
+                months
+            ));
+        }
+        html.push_str("</div>");
+        html
+    }
+
+    /// Shows when an artist, or one of their albums, was listened to and
+    /// lists the journal days with plays.
+    fn music_item(&self, site: &Site, artist: &str, album: Option<&str>) -> Response {
+        let lastfm = self.sources();
+        let show_username = lastfm.analyzers.len() > 1;
+        let sections: Vec<String> = lastfm
+            .analyzers
+            .iter()
+            .filter_map(|analyzer| {
+                self.music_item_html(site, analyzer, artist, album, show_username)
+            })
+            .collect();
+        if sections.is_empty() {
+            return Response::text("No plays found").with_status_code(404);
+        }
+        let title = match album {
+            Some(album) => format!("{} – {}", escape_html(album), escape_html(artist)),
+            None => escape_html(artist),
+        };
+        let mut context = tera::Context::new();
+        context.insert("title", &title);
+        context.insert("period", "");
+        context.insert("from", "");
+        context.insert("to", "");
+        context.insert("sections", &sections.join("\n"));
+        Response::html(site.tera.render("music.html", &context).unwrap())
+    }
+
+    fn music_item_html(
+        &self,
+        site: &Site,
+        analyzer: &LastFmAnalyzer,
+        artist: &str,
+        album: Option<&str>,
+        show_username: bool,
+    ) -> Option<String> {
+        let counts = analyzer.get_play_counts_of(artist, album);
+        let (first, last) = (*counts.keys().next()?, *counts.keys().next_back()?);
+        let mut html = String::from(r#"<div class="music-section">"#);
+        if show_username {
+            html.push_str(&format!("<h2>{}</h2>", escape_html(analyzer.username())));
+        }
+        if let Some(album) = album {
+            html.push_str(&format!(
+                "<p>{}By {}</p>",
+                self.album_art_html(artist, album),
+                artist_link(artist)
+            ));
+        }
+        html.push_str(&format!(
+            r#"<p class="total-tracks">{} plays on {} days · first listened on {} · last listened on {}</p>"#,
+            counts.values().sum::<usize>(),
+            counts.len(),
+            date_link(site.entries, first),
+            date_link(site.entries, last)
+        ));
+
+        // Plays per month from the first to the last listen
+        let mut months = vec![];
+        let mut month = first.with_day(1)?;
+        while month <= last {
+            let next = month.checked_add_months(chrono::Months::new(1));
+            let plays: usize = counts
+                .range((
+                    Bound::Included(month),
+                    next.map_or(Bound::Unbounded, Bound::Excluded),
+                ))
+                .map(|(_, count)| count)
+                .sum();
+            months.push((month, plays));
+            let Some(next) = next else {
+                break;
+            };
+            month = next;
+        }
+        let max = months
+            .iter()
+            .map(|(_, plays)| *plays)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        html.push_str(r#"<h3>Plays per month</h3><div class="hours">"#);
+        for (month, plays) in &months {
+            html.push_str(&format!(
+                r#"<div class="hour" style="height: {}%" title="{} {}: {} plays"></div>"#,
+                plays * 100 / max,
+                calendar::MONTH_NAMES[month.month0() as usize],
+                month.year(),
+                plays
+            ));
+        }
+        html.push_str(&format!(
+            r#"</div><div class="hour-labels"><span>{}</span><span>{}</span></div>"#,
+            first.format("%Y-%m"),
+            last.format("%Y-%m")
+        ));
+
+        let list =
+            |items: Vec<String>| format!(r#"<ul class="stats-list">{}</ul>"#, items.join(""));
+        let tracks: Vec<String> = analyzer
+            .get_tracks_of(artist, album)
+            .into_iter()
+            .map(|track| {
+                format!(
+                    r#"<li><span class="item-name">{}</span> <span class="play-count">({} plays)</span></li>"#,
+                    escape_html(&track.name),
+                    track.play_count
+                )
+            })
+            .collect();
+        let entries = site.entries;
+        let days: Vec<String> = counts
+            .iter()
+            .rev()
+            .filter(|(day, _)| entries.get(**day).is_some())
+            .map(|(day, count)| {
+                format!(
+                    r#"<li><span class="item-name">{}</span> <span class="play-count">({} plays)</span></li>"#,
+                    date_link(site.entries, *day),
+                    count
+                )
+            })
+            .collect();
+        let days = if days.is_empty() {
+            r#"<p class="total-tracks">No journal entries on these days</p>"#.to_owned()
+        } else {
+            list(days)
+        };
+        html.push_str(r#"<div class="stats-columns">"#);
+        html.push_str(&format!("<div><h3>Journal days</h3>{}</div>", days));
+        if album.is_none() {
+            let albums: Vec<String> = analyzer
+                .get_albums_of_artist(artist)
+                .into_iter()
+                .map(|album| {
+                    format!(
+                        r#"<li>{}<span class="item-name">{}</span> <span class="play-count">({} plays)</span></li>"#,
+                        self.album_art_html(&album.artist, &album.name),
+                        album_link(&album.artist, &album.name),
+                        album.play_count
+                    )
+                })
+                .collect();
+            html.push_str(&format!("<div><h3>Albums</h3>{}</div>", list(albums)));
+        }
+        html.push_str(&format!("<div><h3>Tracks</h3>{}</div>", list(tracks)));
+        html.push_str("</div></div>");
+        Some(html)
+    }
+
+    /// Summarizes each month between `from` and `to`, if the range spans
+    /// more than one month. The top album of each month links to the entry
+    /// on which it was played most.
+    fn music_months_html(
+        &self,
+        site: &Site,
+        analyzer: &LastFmAnalyzer,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> String {
+        let Some(mut month) = from.with_day(1) else {
+            return String::new();
+        };
+        if month.year() == to.year() && month.month() == to.month() {
+            return String::new();
+        }
+        let mut rows = vec![];
+        while month <= to {
+            let next = month.checked_add_months(chrono::Months::new(1));
+            let month_end = next.and_then(|next| next.pred_opt()).unwrap_or(to);
+            let (start, end) = (month.max(from), month_end.min(to));
+            let total = analyzer.get_scrobbles_in_range(start, end).len();
+            if total > 0 {
+                let artist = analyzer
+                    .get_top_artists(start, end, 1)
+                    .into_iter()
+                    .next()
+                    .map(|artist| artist_link(&artist.name))
+                    .unwrap_or_default();
+                let album = analyzer
+                    .get_top_albums(start, end, 1)
+                    .into_iter()
+                    .next()
+                    .map(|album| {
+                        let top_day = analyzer
+                            .get_top_day_for_album(&album.artist, &album.name, start, end)
+                            .map(|(day, _)| format!(" ({})", date_link(site.entries, day)))
+                            .unwrap_or_default();
+                        format!(
+                            "{} – {}{}",
+                            artist_link(&album.artist),
+                            album_link(&album.artist, &album.name),
+                            top_day
+                        )
+                    })
+                    .unwrap_or_default();
+                rows.push(format!(
+                    r#"<tr><td><a href="/music?from={}&to={}">{} {}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>"#,
+                    start.format("%Y-%m-%d"),
+                    end.format("%Y-%m-%d"),
+                    calendar::MONTH_NAMES[month.month0() as usize],
+                    month.year(),
+                    total,
+                    artist,
+                    album
+                ));
+            }
+            let Some(next) = next else {
+                break;
+            };
+            month = next;
+        }
+        rows.join("")
+    }
+}
+
+/// Links to the entry of `date` if there is one.
+fn date_link(entries: &Entries, date: NaiveDate) -> String {
+    let exists = entries.get(date).is_some();
+    let date = date.format("%Y-%m-%d").to_string();
+    if exists {
+        format!(r#"<a href="/{}">{}</a>"#, date, date)
+    } else {
+        date
+    }
+}
+
+fn artist_link(artist: &str) -> String {
+    format!(
+        r#"<a href="/music/artist/{}">{}</a>"#,
+        url_segment(artist),
+        escape_html(artist)
+    )
+}
+
+fn album_link(artist: &str, album: &str) -> String {
+    if album.is_empty() {
+        return String::new();
+    }
+    format!(
+        r#"<a href="/music/album/{}/{}">{}</a>"#,
+        url_segment(artist),
+        url_segment(album),
+        escape_html(album)
+    )
+}
@@ -0,0 +1,274 @@
+use serde::Deserialize;
+use std::error::Error;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::lastfm::{LastFmAnalyzer, Scrobble};
+use crate::scrobble_import::{self, Format};
+
+/// Number of scrobbles requested per page, the maximum the API allows.
+const PAGE_SIZE: usize = 200;
+/// How long a request may take before the sync gives up, so that a hanging
+/// connection doesn't block the sync forever.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Last.fm returns a single object instead of a list if there is exactly one.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> OneOrMany<T> {
+    fn into_vec(self) -> Vec<T> {
+        match self {
+            OneOrMany::One(item) => vec![item],
+            OneOrMany::Many(items) => items,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Text {
+    #[serde(rename = "#text")]
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Date {
+    /// Unix timestamp in seconds, as a string.
+    uts: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Track {
+    name: String,
+    artist: Text,
+    album: Text,
+    /// Missing for the track that is currently playing.
+    date: Option<Date>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PageAttributes {
+    #[serde(rename = "totalPages")]
+    total_pages: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecentTracks {
+    #[serde(default)]
+    track: Option<OneOrMany<Track>>,
+    #[serde(rename = "@attr")]
+    attributes: PageAttributes,
+}
+
+#[derive(Debug, Deserialize)]
+struct Response {
+    recenttracks: RecentTracks,
+}
+
+/// A client for the parts of the Last.fm API needed to keep the local
+/// scrobble store up to date. The base url is configurable so that the
+/// sync can run against a local stand-in of the API.
+pub struct Client {
+    agent: ureq::Agent,
+    base_url: String,
+    api_key: String,
+}
+
+impl Client {
+    pub fn new(base_url: &str, api_key: &str) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+            base_url: base_url.to_owned(),
+            api_key: api_key.to_owned(),
+        }
+    }
+
+    fn recent_tracks_page(
+        &self,
+        username: &str,
+        from: Option<i64>,
+        page: usize,
+    ) -> Result<RecentTracks, Box<dyn Error>> {
+        let mut request = self
+            .agent
+            .get(&self.base_url)
+            .query("method", "user.getrecenttracks")
+            .query("user", username)
+            .query("api_key", &self.api_key)
+            .query("format", "json")
+            .query("limit", &PAGE_SIZE.to_string())
+            .query("page", &page.to_string());
+        if let Some(from) = from {
+            request = request.query("from", &from.to_string());
+        }
+        let response: Response = request.call()?.into_json()?;
+        Ok(response.recenttracks)
+    }
+
+    /// Fetches all scrobbles of `username` after the unix timestamp `from`
+    /// (in seconds), or all of them if `from` is `None`.
+    pub fn recent_tracks(
+        &self,
+        username: &str,
+        from: Option<i64>,
+    ) -> Result<Vec<Scrobble>, Box<dyn Error>> {
+        let mut scrobbles = vec![];
+        let mut page = 1;
+        loop {
+            let recent_tracks = self.recent_tracks_page(username, from, page)?;
+            let total_pages: usize = recent_tracks.attributes.total_pages.parse()?;
+            for track in recent_tracks
+                .track
+                .map(OneOrMany::into_vec)
+                .unwrap_or_default()
+            {
+                let Some(date) = track.date else {
+                    continue;
+                };
+                scrobbles.push(Scrobble {
+                    track: track.name,
+                    artist: track.artist.text,
+                    album: track.album.text,
+                    date: date.uts.parse::<i64>()? * 1000,
+                    extra: serde_json::Map::new(),
+                });
+            }
+            if page >= total_pages {
+                return Ok(scrobbles);
+            }
+            page += 1;
+        }
+    }
+}
+
+/// Fetches all scrobbles of `username` newer than the latest one in the
+/// store at `path` and merges them into it, creating the store if it does
+/// not exist yet. Exports of other services are left alone. Returns the
+/// number of scrobbles added.
+pub fn sync(path: &Path, username: &str, client: &Client) -> Result<usize, Box<dyn Error>> {
+    if path.exists() && scrobble_import::file_format(path) != Some(Format::LastFmStats) {
+        return Ok(0);
+    }
+    let mut analyzer = if path.exists() {
+        LastFmAnalyzer::load_from_file(path)?
+    } else {
+        LastFmAnalyzer::new(username)
+    };
+    let from = analyzer.latest_scrobble().map(|ms| ms / 1000 + 1);
+    let scrobbles = client.recent_tracks(username, from)?;
+    let added = analyzer.merge(scrobbles);
+    if added > 0 {
+        analyzer.save_to_file(path)?;
+    }
+    Ok(added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    /// Serves one response per request from `respond`, given the query
+    /// string, and records the query strings.
+    fn mock_api(respond: fn(&str) -> String) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/2.0/", listener.local_addr().unwrap());
+        let queries = Arc::new(Mutex::new(vec![]));
+        let recorded = queries.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 2 {
+                    header.clear();
+                }
+                let target = request_line.split(' ').nth(1).unwrap_or_default();
+                let query = target.split_once('?').map(|(_, query)| query).unwrap_or("");
+                recorded.lock().unwrap().push(query.to_owned());
+                let body = respond(query);
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        (url, queries)
+    }
+
+    fn track(name: &str, uts: i64) -> String {
+        format!(
+            r##"{{"name": "{}", "artist": {{"#text": "Artist"}}, "album": {{"#text": "Album"}}, "date": {{"uts": "{}"}}}}"##,
+            name, uts
+        )
+    }
+
+    fn page(tracks: &str, total_pages: usize) -> String {
+        format!(
+            r#"{{"recenttracks": {{"track": {}, "@attr": {{"totalPages": "{}"}}}}}}"#,
+            tracks, total_pages
+        )
+    }
+
+    fn param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+    }
+
+    #[test]
+    fn fetches_all_pages_and_merges_only_new_scrobbles() {
+        let (url, queries) = mock_api(|query| match (param(query, "from"), param(query, "page")) {
+            // The first sync gets two pages, the second of them with a
+            // single track, which Last.fm returns as an object.
+            (None, Some("1")) => page(
+                &format!(
+                    r##"[{{"name": "Playing", "artist": {{"#text": "Artist"}}, "album": {{"#text": "Album"}}}}, {}, {}]"##,
+                    track("c", 300),
+                    track("b", 200)
+                ),
+                2,
+            ),
+            (None, _) => page(&track("a", 100), 2),
+            // The API includes scrobbles at `from`, so a repeated one
+            // must not be added twice.
+            (Some(_), _) => page(&format!("[{}, {}]", track("d", 400), track("c", 300)), 1),
+        });
+        let client = Client::new(&url, "key");
+        let path =
+            std::env::temp_dir().join(format!("journal-sync-test-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(sync(&path, "user", &client).unwrap(), 3);
+        assert_eq!(sync(&path, "user", &client).unwrap(), 1);
+        let analyzer = LastFmAnalyzer::load_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(analyzer.username(), "user");
+        let day = chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+        let tracks: Vec<&str> = analyzer
+            .get_scrobbles_in_range(day, day)
+            .iter()
+            .map(|scrobble| scrobble.track.as_str())
+            .collect();
+        assert_eq!(tracks, vec!["a", "b", "c", "d"]);
+        let queries = queries.lock().unwrap();
+        assert_eq!(queries.len(), 3);
+        assert_eq!(param(&queries[0], "page"), Some("1"));
+        assert_eq!(param(&queries[1], "page"), Some("2"));
+        assert_eq!(param(&queries[2], "from"), Some("301"));
+        assert_eq!(param(&queries[2], "user"), Some("user"));
+        assert_eq!(param(&queries[2], "api_key"), Some("key"));
+    }
+}
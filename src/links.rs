@@ -0,0 +1,80 @@
+use chrono::NaiveDate;
+
+/// Wiki-style links to other entries look like `[[2023-07-02]]`.
+const LINK_START: &str = "[[";
+const LINK_END: &str = "]]";
+
+fn is_date(target: &str) -> bool {
+    NaiveDate::parse_from_str(target, "%Y-%m-%d").is_ok()
+}
+
+/// Calls `f` for every piece of `content`, either with `Some(date)` for
+/// a valid `[[date]]` link or with `None` for the plain text in between.
+fn split_links<'a>(content: &'a str, mut f: impl FnMut(&'a str, Option<&'a str>)) {
+    let mut rest = content;
+    while let Some(start) = rest.find(LINK_START) {
+        let after_start = &rest[start + LINK_START.len()..];
+        let Some(end) = after_start.find(LINK_END) else {
+            break;
+        };
+        let target = &after_start[..end];
+        if is_date(target) {
+            f(&rest[..start], None);
+            f(
+                &rest[start..start + LINK_START.len() + end + LINK_END.len()],
+                Some(target),
+            );
+        } else {
+            f(&rest[..start + LINK_START.len()], None);
+            rest = after_start;
+            continue;
+        }
+        rest = &after_start[end + LINK_END.len()..];
+    }
+    f(rest, None);
+}
+
+/// Returns the dates of all entries linked from `content`, without duplicates.
+pub fn find_links(content: &str) -> Vec<String> {
+    let mut links: Vec<String> = vec![];
+    split_links(content, |_, target| {
+        if let Some(target) = target {
+            if !links.iter().any(|link| link == target) {
+                links.push(target.to_owned());
+            }
+        }
+    });
+    links
+}
+
+/// Replaces every `[[date]]` link in `content` by a link to the entry page.
+pub fn render_links(content: &str) -> String {
+    let mut result = String::with_capacity(content.len());
+    split_links(content, |text, target| match target {
+        Some(target) => result.push_str(&format!(r#"<a href="/{}">{}</a>"#, target, target)),
+        None => result.push_str(text),
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_valid_date_links_once() {
+        assert_eq!(
+            find_links("See [[2023-07-02]], [[2023-02-30]], [[notes]] and [[2023-07-02]] or [[2023-07-03]]"),
+            vec!["2023-07-02", "2023-07-03"]
+        );
+        assert!(find_links("[[2023-07-02").is_empty());
+    }
+
+    #[test]
+    fn renders_links_and_keeps_other_brackets() {
+        assert_eq!(
+            render_links("[[x]] before [[2023-07-02]] after [["),
+            r#"[[x]] before <a href="/2023-07-02">2023-07-02</a> after [["#
+        );
+    }
+}
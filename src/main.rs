@@ -1,24 +1,192 @@
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use chrono::{Datelike, NaiveDate, NaiveTime};
 
 use entries::Entry;
-use rouille::{router, Response};
+use rouille::input::post::BufferedFile;
+use rouille::{post_input, router, try_or_400, Response};
+use serde::Serialize;
 use tera::Tera;
 
-use crate::entries::Entries;
-use crate::lastfm::LastFmAnalyzer;
+use crate::calendar::{DayDataCount, DayStats, Shading};
+use crate::day_data::{DayDataProvider, Site};
+use crate::editor::SaveError;
+use crate::entries::{Entries, Note};
+use crate::media_info::MediaInfo;
+use crate::playlist::Track;
+use crate::search::{Query, SearchIndex, SnippetPart};
+use crate::web::{escape_html, parse_date_param, url_segment};
 
+mod album_art;
+mod calendar;
 mod config;
+mod day_data;
 mod dump_processor;
+mod editor;
 mod entries;
+mod front_matter;
 mod lastfm;
+mod lastfm_provider;
+mod lastfm_sync;
+mod links;
+mod media_info;
+mod names;
+mod new_entry;
+mod photo_info;
+mod playlist;
+mod scrobble_import;
+mod search;
+mod tags;
+mod watcher;
+mod web;
+
+#[derive(Serialize)]
+struct UploadResult {
+    dates: Vec<String>,
+    errors: Vec<String>,
+}
 
 struct Manager {
-    entries: Entries,
+    entries: RwLock<Arc<Entries>>,
     tera: Tera,
-    lastfm: Option<LastFmAnalyzer>,
+    providers: Vec<Box<dyn DayDataProvider>>,
+    search_index: RwLock<Arc<SearchIndex>>,
+    /// Held while the entries or the search index are rebuilt, so that
+    /// concurrent reloads don't undo each other.
+    reloading: Mutex<()>,
+    /// Fingerprints of all folders as of the last reload, which open
+    /// entry pages poll to know when to refresh themselves.
+    fingerprints: RwLock<watcher::Fingerprints>,
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(|arg| arg.as_str()) {
+        None | Some("serve") => serve(),
+        Some("search") => search(&args[1..].join(" ")),
+        Some("new") => new(args.get(1).map(|date| date.as_str())),
+        Some("sync") => sync(),
+        Some(command) => {
+            eprintln!("Unknown command: {}", command);
+            eprintln!("Usage: journal [serve | search <query> | new [YYYY-MM-DD] | sync]");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn load_providers() -> Vec<Box<dyn DayDataProvider>> {
+    let providers = day_data::providers();
+    for provider in &providers {
+        provider.load();
+        for status in provider.status() {
+            println!("Warning: {}", status);
+        }
+    }
+    providers
+}
+
+fn read_entries(providers: &[Box<dyn DayDataProvider>]) -> Entries {
+    let provider_files = providers
+        .iter()
+        .flat_map(|provider| provider.journal_files())
+        .collect();
+    Entries::read(Path::new(config::JOURNAL_PATH), provider_files).unwrap()
+}
+
+fn sync() {
+    let providers: Vec<_> = day_data::providers()
+        .into_iter()
+        .filter(|provider| provider.fetch_interval().is_some())
+        .collect();
+    if providers.is_empty() {
+        eprintln!("No provider is configured to fetch new data");
+        std::process::exit(1);
+    }
+    let mut failed = false;
+    for provider in &providers {
+        match provider.fetch() {
+            Ok(added) => println!("{}: added {} new items", provider.name(), added),
+            Err(e) => {
+                eprintln!("Could not fetch new {} data: {}", provider.name(), e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+fn providers_search_text(
+    providers: &[Box<dyn DayDataProvider>],
+    dates: &[NaiveDate],
+) -> HashMap<String, Vec<String>> {
+    let mut extra_text: HashMap<String, Vec<String>> = HashMap::new();
+    for provider in providers {
+        for (date, text) in provider.search_text(dates) {
+            extra_text.entry(date).or_default().extend(text);
+        }
+    }
+    extra_text
+}
+
+fn build_search_index(entries: &Entries, providers: &[Box<dyn DayDataProvider>]) -> SearchIndex {
+    let dates: Vec<NaiveDate> = entries.iter().map(|entry| entry.date()).collect();
+    SearchIndex::build(entries, &providers_search_text(providers, &dates))
+}
+
+fn search(query: &str) {
+    let providers = load_providers();
+    let entries = read_entries(&providers);
+    let search_index = build_search_index(&entries, &providers);
+    let results = search_index.search(&Query::parse(query));
+    for result in &results {
+        let snippet: String = result
+            .snippet
+            .iter()
+            .map(|(text, highlighted)| {
+                if *highlighted {
+                    format!("\x1b[1m{}\x1b[0m", text)
+                } else {
+                    text.clone()
+                }
+            })
+            .collect();
+        println!("{}  {}", result.date, snippet.replace('\n', " "));
+    }
+    println!("{} results", results.len());
+}
+
+fn parse_date_or_today(date: Option<&str>) -> Option<NaiveDate> {
+    match date {
+        Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d").ok(),
+        None => Some(chrono::Local::now().date_naive()),
+    }
+}
+
+/// Creates the entry for `date` (today by default) and opens it in `$EDITOR`.
+fn new(date: Option<&str>) {
+    let Some(date) = parse_date_or_today(date) else {
+        eprintln!("Invalid date, expected YYYY-MM-DD");
+        std::process::exit(1);
+    };
+    let path = match new_entry::create_entry(date) {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("Could not create entry: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let editor = std::env::var("EDITOR").unwrap_or("vi".to_owned());
+    if let Err(e) = std::process::Command::new(&editor).arg(&path).status() {
+        eprintln!("Could not run {}: {}", editor, e);
+        std::process::exit(1);
+    }
+}
+
+fn serve() {
     println!("Processing dump folder...");
     if let Err(e) = dump_processor::process_dump_folder() {
         eprintln!("Error processing dump folder: {}", e);
@@ -26,13 +194,34 @@ fn main() {
 
     println!("Now listening on localhost:8000");
 
-    let entries = Entries::read(&Path::new(config::JOURNAL_PATH)).unwrap();
+    let providers = load_providers();
+    let entries = read_entries(&providers);
     let mut tera = Tera::default();
     // Embedding these here for simplicity, so I can just run the binary from anywhere
     tera.add_raw_template("pic.html", include_str!("../templates/pic.html"))
         .unwrap();
+    tera.add_raw_template("upload.html", include_str!("../templates/upload.html"))
+        .unwrap();
     tera.add_raw_template("audio.html", include_str!("../templates/audio.html"))
         .unwrap();
+    tera.add_raw_template("note.html", include_str!("../templates/note.html"))
+        .unwrap();
+    tera.add_raw_template("lightbox.html", include_str!("../templates/lightbox.html"))
+        .unwrap();
+    tera.add_raw_template("player.html", include_str!("../templates/player.html"))
+        .unwrap();
+    tera.add_raw_template("music.html", include_str!("../templates/music.html"))
+        .unwrap();
+    tera.add_raw_template(
+        "slideshow.html",
+        include_str!("../templates/slideshow.html"),
+    )
+    .unwrap();
+    tera.add_raw_template(
+        "media_info.html",
+        include_str!("../templates/media_info.html"),
+    )
+    .unwrap();
     tera.add_raw_template("entry.html", include_str!("../templates/entry.html"))
         .unwrap();
     tera.add_raw_template(
@@ -40,34 +229,190 @@ fn main() {
         include_str!("../templates/dashboard.html"),
     )
     .unwrap();
+    tera.add_raw_template("list.html", include_str!("../templates/list.html"))
+        .unwrap();
+    tera.add_raw_template("edit.html", include_str!("../templates/edit.html"))
+        .unwrap();
+    tera.add_raw_template("search.html", include_str!("../templates/search.html"))
+        .unwrap();
+    tera.add_raw_template("calendar.html", include_str!("../templates/calendar.html"))
+        .unwrap();
     tera.autoescape_on(vec![]);
 
-    // Try to load LastFm data
-    let lastfm_path = Path::new(config::JOURNAL_PATH).join("lastfmstats-Tehforsch.json");
-    let lastfm = LastFmAnalyzer::load_from_file(&lastfm_path)
-        .map_err(|e| {
-            println!("Warning: Could not load LastFm data: {}", e);
-            e
-        })
-        .ok();
+    let search_index = build_search_index(&entries, &providers);
 
-    let manager = Manager {
-        entries,
+    let manager = Arc::new(Manager {
+        entries: RwLock::new(Arc::new(entries)),
         tera,
-        lastfm,
-    };
+        providers,
+        search_index: RwLock::new(Arc::new(search_index)),
+        reloading: Mutex::new(()),
+        fingerprints: RwLock::new(watcher::journal_fingerprints(Path::new(
+            config::JOURNAL_PATH,
+        ))),
+    });
+
+    let watched_manager = manager.clone();
+    let watched = watcher::watch(
+        config::JOURNAL_PATH.into(),
+        config::WATCH_DELAY,
+        move |names| {
+            let journal = Path::new(config::JOURNAL_PATH);
+            let fingerprints: HashMap<String, Option<u64>> = names
+                .into_iter()
+                .map(|name| {
+                    let fingerprint = watcher::fingerprint(journal, &name);
+                    (name, fingerprint)
+                })
+                .collect();
+            let changed = watcher::changed_folders(
+                &watched_manager.fingerprints.read().unwrap(),
+                &fingerprints,
+            );
+            if !changed.is_empty() {
+                watched_manager.reload_folders(&changed);
+            }
+        },
+    );
+    if let Err(e) = watched {
+        eprintln!("Warning: Could not watch the journal for changes: {}", e);
+    }
+
+    for index in 0..manager.providers.len() {
+        let Some(interval) = manager.providers[index].fetch_interval() else {
+            continue;
+        };
+        let fetching_manager = manager.clone();
+        std::thread::spawn(move || loop {
+            let provider = &fetching_manager.providers[index];
+            match provider.fetch() {
+                Ok(0) => {}
+                Ok(added) => {
+                    println!("Fetched {} new items from {}", added, provider.name());
+                    fetching_manager.reload_provider(provider.as_ref());
+                }
+                Err(e) => eprintln!("Could not fetch new {} data: {}", provider.name(), e),
+            }
+            std::thread::sleep(interval);
+        });
+    }
 
     rouille::start_server("localhost:8000", move |request| {
+        if let Some(response) = manager.provider_response(request) {
+            return response;
+        }
         {
             router!(request,
                 (GET) (/dashboard) => {
                     Response::html(manager.dashboard_html())
                 },
+                (POST) (/new) => {
+                    let input = try_or_400!(post_input!(request, {
+                        date: Option<String>,
+                    }));
+                    manager.create_entry(input.date.filter(|date| !date.is_empty()))
+                },
+                (POST) (/upload) => {
+                    let input = try_or_400!(post_input!(request, {
+                        files: Vec<BufferedFile>,
+                        date: Option<String>,
+                    }));
+                    manager.upload(input.files, input.date.filter(|date| !date.is_empty()))
+                },
+                (GET) (/search) => {
+                    Response::html(manager.search_html(
+                        &request.get_param("q").unwrap_or_default(),
+                        request.get_param("from"),
+                        request.get_param("to"),
+                    ))
+                },
+                (GET) (/calendar) => {
+                    manager.calendar(None, None, request.get_param("shade"))
+                },
+                (GET) (/calendar/{year: i32}) => {
+                    manager.calendar(Some(year), None, request.get_param("shade"))
+                },
+                (GET) (/calendar/{year: i32}/{month: u32}) => {
+                    manager.calendar(Some(year), Some(month), request.get_param("shade"))
+                },
+                (GET) (/onthisday/{month_day: String}) => {
+                    manager.on_this_day(&month_day, request.get_param("tolerance"))
+                },
+                (GET) (/photo-info) => {
+                    manager.photo_info(request.get_param("file").unwrap_or_default())
+                },
+                (GET) (/slideshow) => {
+                    manager.slideshow(request.get_param("from"), request.get_param("to"))
+                },
+                (GET) (/playlist) => {
+                    let base_url = format!("http://{}", request.header("Host").unwrap_or("localhost:8000"));
+                    manager.playlist(
+                        request.get_param("from"),
+                        request.get_param("to"),
+                        request.get_param("tag"),
+                        request.get_param("format"),
+                        &base_url,
+                    )
+                },
+                (GET) (/player) => {
+                    manager.player(
+                        request.get_param("from"),
+                        request.get_param("to"),
+                        request.get_param("tag"),
+                    )
+                },
+                (GET) (/health) => {
+                    Response::html(manager.health_html())
+                },
+                (GET) (/tags) => {
+                    Response::html(manager.tags_html())
+                },
+                (GET) (/tags/{tag: String}) => {
+                    manager.entries_for_tag(tag)
+                },
+                (GET) (/{date: String}/version) => {
+                    manager.entry_version(date)
+                },
+                (GET) (/{date: String}/edit) => {
+                    manager.edit_entry(date)
+                },
+                (POST) (/{date: String}/edit) => {
+                    let input = try_or_400!(post_input!(request, {
+                        content: String,
+                        version: String,
+                    }));
+                    manager.save_entry(date, input.content, input.version)
+                },
+                (POST) (/{date: String}/media) => {
+                    let input = try_or_400!(post_input!(request, {
+                        file: String,
+                        caption: String,
+                        alt: Option<String>,
+                        people: String,
+                        favourite: bool,
+                    }));
+                    let non_empty = |text: String| Some(text.trim().to_owned()).filter(|text| !text.is_empty());
+                    let info = MediaInfo {
+                        caption: non_empty(input.caption),
+                        alt: input.alt.and_then(non_empty),
+                        people: input.people.split(',').filter_map(|name| non_empty(name.to_owned())).collect(),
+                        favourite: input.favourite,
+                    };
+                    manager.save_media_info(date, input.file, info)
+                },
+                (POST) (/{date: String}/preview) => {
+                    let input = try_or_400!(post_input!(request, {
+                        content: String,
+                    }));
+                    // Rendered exactly like the entry page, which shows the text with its
+                    // line breaks and date links rather than as Markdown.
+                    Response::html(render_text(front_matter::split(&input.content).1))
+                },
                 (GET) (/{date: String}) => {
                     manager.entry_for_date(date)
                 },
                 _ => {
-                    let response = rouille::match_assets(&request, config::JOURNAL_PATH);
+                    let response = rouille::match_assets(request, config::JOURNAL_PATH);
                     if response.is_success() {
                         response
                     }
@@ -81,12 +426,100 @@ fn main() {
 }
 
 impl Manager {
+    fn entries(&self) -> Arc<Entries> {
+        self.entries.read().unwrap().clone()
+    }
+
+    /// Re-reads the data of a provider after fetching new data and rebuilds
+    /// the search index, which contains the text of the providers.
+    fn reload_provider(&self, provider: &dyn DayDataProvider) {
+        provider.load();
+        for status in provider.status() {
+            println!("Warning: {}", status);
+        }
+        let _reloading = self.reloading.lock().unwrap();
+        let search_index = build_search_index(&self.entries(), &self.providers);
+        *self.search_index.write().unwrap() = Arc::new(search_index);
+    }
+
+    fn provider_status(&self) -> Vec<String> {
+        self.providers
+            .iter()
+            .flat_map(|provider| provider.status())
+            .collect()
+    }
+
+    fn provider_response(&self, request: &rouille::Request) -> Option<Response> {
+        let entries = self.entries();
+        let site = Site {
+            entries: &entries,
+            tera: &self.tera,
+        };
+        self.providers
+            .iter()
+            .find_map(|provider| provider.response(request, &site))
+    }
+
+    fn search_index(&self) -> Arc<SearchIndex> {
+        self.search_index.read().unwrap().clone()
+    }
+
+    /// Re-reads the folders of the journal with the given names from disk,
+    /// updates the search index for them and remembers their fingerprints,
+    /// so that the watcher doesn't reload them again.
+    fn reload_folders(&self, names: &[String]) {
+        let journal = Path::new(config::JOURNAL_PATH);
+        let _reloading = self.reloading.lock().unwrap();
+        let fingerprints: Vec<(&String, Option<u64>)> = names
+            .iter()
+            .map(|name| (name, watcher::fingerprint(journal, name)))
+            .collect();
+        let dates: Vec<NaiveDate> = names
+            .iter()
+            .filter_map(|name| NaiveDate::parse_from_str(name, "%Y-%m-%d").ok())
+            .collect();
+        let updated = self.entries().reread(journal, names);
+        let search_index = self.search_index().update(
+            &updated,
+            &providers_search_text(&self.providers, &dates),
+            names,
+        );
+        *self.entries.write().unwrap() = Arc::new(updated);
+        *self.search_index.write().unwrap() = Arc::new(search_index);
+        let mut known = self.fingerprints.write().unwrap();
+        for (name, fingerprint) in fingerprints {
+            match fingerprint {
+                Some(fingerprint) => known.insert(name.clone(), fingerprint),
+                None => known.remove(name),
+            };
+        }
+    }
+
+    fn entry_version(&self, date: String) -> Response {
+        match self.fingerprints.read().unwrap().get(&date) {
+            Some(fingerprint) => Response::text(format!("{:016x}", fingerprint)),
+            None => self.response_404(),
+        }
+    }
+
+    fn create_entry(&self, date: Option<String>) -> Response {
+        let Some(date) = parse_date_or_today(date.as_deref()) else {
+            return Response::html("Invalid date.").with_status_code(400);
+        };
+        if let Err(e) = new_entry::create_entry(date) {
+            return Response::html(format!("Could not create entry: {}", e)).with_status_code(500);
+        }
+        self.reload_folders(&[date.format("%Y-%m-%d").to_string()]);
+        Response::redirect_303(date.format("/%Y-%m-%d/edit").to_string())
+    }
+
     fn response_404(&self) -> Response {
         Response::html("404 error.").with_status_code(404)
     }
 
     fn entry_for_date(&self, date: String) -> Response {
-        let entry = self.entries.get_by_date(date);
+        let entries = self.entries();
+        let entry = entries.get_by_date(date);
         if let Some(entry) = entry {
             Response::html(self.entry_html(entry))
         } else {
@@ -94,12 +527,543 @@ impl Manager {
         }
     }
 
+    /// Stores uploaded files in the dump folder and imports them from there,
+    /// either into the entry for `date` or into the entry for the date
+    /// detected from each file, just like files put into the dump folder.
+    fn upload(&self, files: Vec<BufferedFile>, date: Option<String>) -> Response {
+        if let Some(ref date) = date {
+            if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+                return Response::html("Invalid date.").with_status_code(400);
+            }
+        }
+        let dump_folder = Path::new(config::DUMP_FOLDER_PATH);
+        if let Err(e) = std::fs::create_dir_all(dump_folder) {
+            return Response::html(format!("Could not create dump folder: {}", e))
+                .with_status_code(500);
+        }
+        let mut result = UploadResult {
+            dates: vec![],
+            errors: vec![],
+        };
+        for file in files {
+            let Some(file_name) = file
+                .filename
+                .as_deref()
+                .and_then(|name| name.rsplit(['/', '\\']).next())
+                .filter(|name| !name.is_empty() && !name.starts_with('.'))
+            else {
+                result.errors.push("File without a valid name".to_owned());
+                continue;
+            };
+            if !dump_processor::is_supported(Path::new(file_name)) {
+                result
+                    .errors
+                    .push(format!("{}: Unsupported file type", file_name));
+                continue;
+            }
+            let path = dump_processor::unique_path(dump_folder, file_name);
+            let imported = std::fs::write(&path, &file.data)
+                .map_err(|e| e.into())
+                .and_then(|_| dump_processor::import_file(&path, date.as_deref()));
+            match imported {
+                Ok(target_date) => {
+                    if !result.dates.contains(&target_date) {
+                        result.dates.push(target_date);
+                    }
+                }
+                Err(e) => result.errors.push(format!("{}: {}", file_name, e)),
+            }
+        }
+        self.reload_folders(&result.dates);
+        Response::json(&result)
+    }
+
+    fn edit_entry(&self, date: String) -> Response {
+        let entries = self.entries();
+        let Some(entry) = entries.get_by_date(date) else {
+            return self.response_404();
+        };
+        let content = entry.content().unwrap_or_default();
+        let version = editor::content_version(&content);
+        Response::html(self.edit_html(entry, &content, &version, None))
+    }
+
+    fn save_entry(&self, date: String, content: String, version: String) -> Response {
+        let entries = self.entries();
+        let Some(entry) = entries.get_by_date(date) else {
+            return self.response_404();
+        };
+        // Browsers submit textareas with CRLF line endings.
+        let content = content.replace("\r\n", "\n");
+        match editor::save(entry.text_path(), &content, &version) {
+            Ok(()) => {
+                self.reload_folders(&[entry.date_str()]);
+                Response::redirect_303(format!("/{}", entry.date_str()))
+            }
+            Err(SaveError::Conflict { current }) => {
+                // Show the editor again with the submitted text. Saving once
+                // more deliberately overwrites the version now on disk.
+                let version = editor::content_version(&current);
+                Response::html(self.edit_html(entry, &content, &version, Some(&current)))
+                    .with_status_code(409)
+            }
+            Err(e) => Response::html(format!("Could not save entry: {}", e)).with_status_code(500),
+        }
+    }
+
+    fn save_media_info(&self, date: String, file: String, info: MediaInfo) -> Response {
+        let entries = self.entries();
+        let Some(entry) = entries.get_by_date(date) else {
+            return self.response_404();
+        };
+        if !entry.has_media(&file) {
+            return Response::text(format!("{} has no file {}", entry.date_str(), file))
+                .with_status_code(400);
+        }
+        let folder = Path::new(config::JOURNAL_PATH).join(entry.date_str());
+        match media_info::update(&folder, &file, info) {
+            Ok(()) => {
+                self.reload_folders(&[entry.date_str()]);
+                Response::redirect_303(format!("/{}", entry.date_str()))
+            }
+            Err(e) => {
+                Response::text(format!("Could not save media details: {}", e)).with_status_code(500)
+            }
+        }
+    }
+
+    fn edit_html(
+        &self,
+        entry: &Entry,
+        content: &str,
+        version: &str,
+        conflict: Option<&str>,
+    ) -> String {
+        let mut context = tera::Context::new();
+        context.insert("date", &entry.date_str());
+        context.insert("content", &escape_html(content));
+        context.insert("version", version);
+        context.insert("preview", &render_text(front_matter::split(content).1));
+        context.insert("conflict", &conflict.map(escape_html).unwrap_or_default());
+        self.tera.render("edit.html", &context).unwrap()
+    }
+
+    fn entries_for_tag(&self, tag: String) -> Response {
+        let all_entries = self.entries();
+        let entries = all_entries.get_by_tag(&tag);
+        if entries.is_empty() {
+            return self.response_404();
+        }
+        let mut context = tera::Context::new();
+        context.insert(
+            "title",
+            &format!("#{}", escape_html(&tags::normalize(&tag))),
+        );
+        context.insert("tags", "");
+        if entries.iter().any(|entry| !entry.audio().is_empty()) {
+            context.insert(
+                "actions",
+                &format!(
+                    r#"<a href="/player?tag={}" class="action-link">Play all audio</a>"#,
+                    url_segment(&tags::normalize(&tag))
+                ),
+            );
+        }
+        context.insert(
+            "entries",
+            &entries
+                .into_iter()
+                .map(|entry| self.dashboard_entry_preview(entry))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        Response::html(self.tera.render("list.html", &context).unwrap())
+    }
+
+    fn health_html(&self) -> String {
+        let entries = self.entries();
+        let warnings: Vec<String> = self
+            .provider_status()
+            .into_iter()
+            .chain(entries.warnings().iter().map(|warning| warning.to_string()))
+            .collect();
+        let mut context = tera::Context::new();
+        context.insert(
+            "title",
+            &format!(
+                "{} entries, {} warnings",
+                entries.iter().count(),
+                warnings.len()
+            ),
+        );
+        context.insert("tags", "");
+        context.insert(
+            "entries",
+            &warnings
+                .iter()
+                .map(|warning| {
+                    format!(
+                        r#"<div class="entry-preview" style="color: #6c757d;">{}</div>"#,
+                        escape_html(warning)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        self.tera.render("list.html", &context).unwrap()
+    }
+
+    fn tags_html(&self) -> String {
+        let entries = self.entries();
+        let mut context = tera::Context::new();
+        context.insert("title", "Tags");
+        context.insert(
+            "tags",
+            &entries
+                .tags()
+                .map(|(tag, count)| self.tag_chip(tag, Some(count)))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        context.insert("entries", "");
+        self.tera.render("list.html", &context).unwrap()
+    }
+
+    fn tag_chip(&self, tag: &str, count: Option<usize>) -> String {
+        let count = count
+            .map(|count| format!(r#" <span class="tag-count">{}</span>"#, count))
+            .unwrap_or_default();
+        format!(
+            r#"<a href="/tags/{}" class="tag-chip">#{}{}</a>"#,
+            url_segment(tag),
+            escape_html(tag),
+            count
+        )
+    }
+
+    fn search_html(&self, q: &str, from: Option<String>, to: Option<String>) -> String {
+        let mut query = Query::parse(q);
+        let parse_date = |date: Option<String>| {
+            date.and_then(|date| chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok())
+        };
+        query.from = parse_date(from).or(query.from);
+        query.to = parse_date(to).or(query.to);
+        let results = self.search_index().search(&query);
+        let mut context = tera::Context::new();
+        context.insert("q", &escape_html(q));
+        context.insert(
+            "from",
+            &query.from.map(|date| date.to_string()).unwrap_or_default(),
+        );
+        context.insert(
+            "to",
+            &query.to.map(|date| date.to_string()).unwrap_or_default(),
+        );
+        context.insert("searched", &!query.is_empty());
+        context.insert("num_results", &results.len());
+        context.insert(
+            "results",
+            &results
+                .iter()
+                .map(|result| {
+                    format!(
+                        r#"<div class="entry-preview">
+                <div style="font-weight: 600; color: #4facfe; margin-bottom: 0.5rem;">
+                    <a href="/{}" style="text-decoration: none; color: inherit;">{}</a>
+                </div>
+                <div style="color: #6c757d; line-height: 1.6;">
+                    {}
+                </div>
+            </div>"#,
+                        result.date,
+                        result.date,
+                        snippet_html(&result.snippet)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        self.tera.render("search.html", &context).unwrap()
+    }
+
+    fn calendar_days(&self) -> HashMap<NaiveDate, DayStats> {
+        let entries = self.entries();
+        let first = entries.iter().map(|entry| entry.date()).min();
+        let last = entries.iter().map(|entry| entry.date()).max();
+        let counts: Vec<_> = match (first, last) {
+            (Some(first), Some(last)) => self
+                .providers
+                .iter()
+                .map(|provider| (provider.calendar_label(), provider.counts(first, last)))
+                .collect(),
+            _ => vec![],
+        };
+        entries
+            .iter()
+            .map(|entry| {
+                let stats = DayStats {
+                    text_length: entry.text_length(),
+                    num_pics: entry.pics().len(),
+                    num_audio: entry.audio().len(),
+                    day_data: counts
+                        .iter()
+                        .map(|((icon, label), counts)| DayDataCount {
+                            icon,
+                            label,
+                            count: counts.get(&entry.date()).copied().unwrap_or(0),
+                        })
+                        .collect(),
+                };
+                (entry.date(), stats)
+            })
+            .collect()
+    }
+
+    /// Shows the year grid, or the month view if `month` is given. Without
+    /// a year, the year of the latest entry is shown.
+    fn calendar(&self, year: Option<i32>, month: Option<u32>, shade: Option<String>) -> Response {
+        let shading = Shading::from_param(shade.as_deref());
+        let days = self.calendar_days();
+        let year = year
+            .or_else(|| days.keys().max().map(|date| date.year()))
+            .unwrap_or_else(|| chrono::Local::now().year());
+        let mut context = tera::Context::new();
+        let shade_query = format!("?shade={}", shading.param());
+        match month {
+            Some(month) => {
+                let Some(first) = NaiveDate::from_ymd_opt(year, month, 1) else {
+                    return self.response_404();
+                };
+                let prev = first.pred_opt().unwrap_or(first);
+                let next = first + chrono::Months::new(1);
+                context.insert(
+                    "title",
+                    &format!("{} {}", calendar::MONTH_NAMES[month as usize - 1], year),
+                );
+                context.insert("base_link", &format!("/calendar/{}/{}", year, month));
+                context.insert(
+                    "link_prev",
+                    &format!("/calendar/{}/{}{}", prev.year(), prev.month(), shade_query),
+                );
+                context.insert(
+                    "link_next",
+                    &format!("/calendar/{}/{}{}", next.year(), next.month(), shade_query),
+                );
+                context.insert("link_up", &format!("/calendar/{}{}", year, shade_query));
+                context.insert(
+                    "range_query",
+                    &format!(
+                        "?from={}&to={}",
+                        first.format("%Y-%m-%d"),
+                        (next - chrono::Days::new(1)).format("%Y-%m-%d")
+                    ),
+                );
+                context.insert(
+                    "months",
+                    &calendar::month_html(year, month, &days, shading, true),
+                );
+            }
+            None => {
+                context.insert("title", &year.to_string());
+                context.insert("base_link", &format!("/calendar/{}", year));
+                context.insert(
+                    "link_prev",
+                    &format!("/calendar/{}{}", year - 1, shade_query),
+                );
+                context.insert(
+                    "link_next",
+                    &format!("/calendar/{}{}", year + 1, shade_query),
+                );
+                context.insert("link_up", "");
+                context.insert(
+                    "range_query",
+                    &format!("?from={}-01-01&to={}-12-31", year, year),
+                );
+                let months = (1..=12)
+                    .map(|month| {
+                        format!(
+                            r#"<div class="month-container"><a href="/calendar/{}/{}{}" class="month-name">{}</a>{}</div>"#,
+                            year,
+                            month,
+                            shade_query,
+                            calendar::MONTH_NAMES[month as usize - 1],
+                            calendar::month_html(year, month, &days, shading, false)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                context.insert("months", &months);
+            }
+        }
+        context.insert("shade", shading.param());
+        Response::html(self.tera.render("calendar.html", &context).unwrap())
+    }
+
+    fn photo_info(&self, file: String) -> Response {
+        let entries = self.entries();
+        let path = PathBuf::from(file);
+        let is_pic = path
+            .iter()
+            .next()
+            .and_then(|date| entries.get_by_date(date.to_string_lossy().into_owned()))
+            .is_some_and(|entry| entry.pics().contains(&path));
+        if !is_pic {
+            return self.response_404();
+        }
+        Response::json(&photo_info::read(
+            &Path::new(config::JOURNAL_PATH).join(path),
+        ))
+    }
+
+    fn slideshow(&self, from: Option<String>, to: Option<String>) -> Response {
+        let entries = self.entries();
+        let entries: Vec<&Entry> = entries
+            .range(parse_date_param(from), parse_date_param(to))
+            .filter(|entry| !entry.pics().is_empty())
+            .collect();
+        let days = entries
+            .iter()
+            .map(|entry| {
+                let pics = entry
+                    .pics()
+                    .iter()
+                    .map(|pic| {
+                        let info = entry.media_info(pic);
+                        format!(
+                            r#"<img src="/{}" alt="{}" loading="lazy" data-lightbox data-file="{}" data-caption="{}" data-date="{}" />"#,
+                            pic.display(),
+                            escape_html(info.alt.as_deref().or(info.caption.as_deref()).unwrap_or("Journal image")),
+                            pic.display(),
+                            escape_html(info.caption.as_deref().unwrap_or("")),
+                            entry.date_str()
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!(
+                    r#"<div class="day"><a href="/{}" class="day-link">{}</a><div class="gallery">{}</div></div>"#,
+                    entry.date_str(),
+                    entry.date_str(),
+                    pics
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let title = match (entries.first(), entries.last()) {
+            (Some(first), Some(last)) if first.date() == last.date() => {
+                format!("Pictures of {}", first.date_str())
+            }
+            (Some(first), Some(last)) => {
+                format!("Pictures {} – {}", first.date_str(), last.date_str())
+            }
+            _ => "No pictures in this range".to_owned(),
+        };
+        let mut context = tera::Context::new();
+        context.insert("title", &title);
+        context.insert("days", &days);
+        Response::html(self.tera.render("slideshow.html", &context).unwrap())
+    }
+
+    fn playlist_tracks(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        tag: Option<&str>,
+    ) -> Vec<Track> {
+        let entries = self.entries();
+        let tag = tag.map(tags::normalize);
+        playlist::tracks(
+            entries
+                .range(from, to)
+                .filter(|entry| tag.as_ref().is_none_or(|tag| entry.tags().contains(tag))),
+        )
+    }
+
+    /// Returns the playlist as M3U if `format` is `m3u` and as JSON otherwise.
+    fn playlist(
+        &self,
+        from: Option<String>,
+        to: Option<String>,
+        tag: Option<String>,
+        format: Option<String>,
+        base_url: &str,
+    ) -> Response {
+        let tracks =
+            self.playlist_tracks(parse_date_param(from), parse_date_param(to), tag.as_deref());
+        match format.as_deref() {
+            Some("m3u") => Response::from_data("audio/x-mpegurl", playlist::m3u(&tracks, base_url))
+                .with_content_disposition_attachment("journal.m3u"),
+            _ => Response::json(&tracks),
+        }
+    }
+
+    fn player(&self, from: Option<String>, to: Option<String>, tag: Option<String>) -> Response {
+        let (from, to) = (parse_date_param(from), parse_date_param(to));
+        let tag = tag
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| tags::normalize(&tag));
+        let tracks = self.playlist_tracks(from, to, tag.as_deref());
+        let tracks_html = tracks
+            .iter()
+            .enumerate()
+            .map(|(i, track)| {
+                let time = [
+                    track.recorded.clone(),
+                    track
+                        .duration
+                        .map(|duration| format!("{}:{:02}", duration / 60, duration % 60)),
+                ]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" · ");
+                format!(
+                    r#"<li class="track" data-index="{}"><span class="track-date"><a href="/{}">{}</a></span><span class="track-title">{}</span><span class="track-time">{}</span></li>"#,
+                    i,
+                    track.date,
+                    track.date,
+                    escape_html(&track.title),
+                    time
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut title = "Audio".to_owned();
+        if let Some(tag) = &tag {
+            title.push_str(&format!(" tagged #{}", escape_html(tag)));
+        }
+        if let Some(from) = from {
+            title.push_str(&format!(" from {}", from.format("%Y-%m-%d")));
+        }
+        if let Some(to) = to {
+            title.push_str(&format!(" until {}", to.format("%Y-%m-%d")));
+        }
+        let mut context = tera::Context::new();
+        context.insert("title", &title);
+        context.insert("tracks", &tracks_html);
+        // Keep a title containing "</script>" from ending the script early.
+        context.insert(
+            "tracks_json",
+            &serde_json::to_string(&tracks)
+                .unwrap()
+                .replace('<', "\\u003c"),
+        );
+        context.insert(
+            "from",
+            &from.map(|from| from.format("%Y-%m-%d").to_string()),
+        );
+        context.insert("to", &to.map(|to| to.format("%Y-%m-%d").to_string()));
+        context.insert("tag", &tag);
+        Response::html(self.tera.render("player.html", &context).unwrap())
+    }
+
     fn dashboard_html(&self) -> String {
+        let entries = self.entries();
         let mut context = tera::Context::new();
         let num_entries = 3;
         let result = (0..num_entries)
             .map(|_| {
-                let random_entry = self.entries.random();
+                let random_entry = entries.random();
                 random_entry
                     .map(|entry| self.dashboard_entry_preview(entry))
                     .unwrap_or("".to_owned())
@@ -107,141 +1071,338 @@ impl Manager {
             .collect::<Vec<_>>()
             .join("\n");
         context.insert("entries", &result);
+        context.insert("upload_date", "");
+        let provider_status = self.provider_status();
+        context.insert(
+            "num_warnings",
+            &(entries.warnings().len() + provider_status.len()),
+        );
+        context.insert(
+            "provider_status",
+            &provider_status
+                .iter()
+                .map(|status| escape_html(status))
+                .collect::<Vec<_>>(),
+        );
+        let today = chrono::Local::now().date_naive();
+        let on_this_day = entries
+            .on_this_day(
+                today.month(),
+                today.day(),
+                config::ON_THIS_DAY_TOLERANCE_DAYS,
+            )
+            .into_iter()
+            .filter(|(year, _)| *year < today.year())
+            .map(|(_, entry)| self.dashboard_entry_preview(entry))
+            .collect::<Vec<_>>()
+            .join("\n");
+        context.insert("on_this_day", &on_this_day);
+        context.insert(
+            "on_this_day_link",
+            &today.format("/onthisday/%m-%d").to_string(),
+        );
         self.tera.render("dashboard.html", &context).unwrap()
     }
 
+    fn on_this_day(&self, month_day: &str, tolerance: Option<String>) -> Response {
+        let Some((month, day)) = month_day
+            .split_once('-')
+            .and_then(|(month, day)| Some((month.parse().ok()?, day.parse().ok()?)))
+            .filter(|(month, day)| NaiveDate::from_ymd_opt(2000, *month, *day).is_some())
+        else {
+            return self.response_404();
+        };
+        let tolerance = tolerance
+            .and_then(|tolerance| tolerance.parse().ok())
+            .unwrap_or(config::ON_THIS_DAY_TOLERANCE_DAYS);
+        let entries = self.entries();
+        let mut context = tera::Context::new();
+        context.insert(
+            "title",
+            &format!(
+                "On this day: {} {}",
+                day,
+                calendar::MONTH_NAMES[month as usize - 1]
+            ),
+        );
+        context.insert("tags", "");
+        context.insert(
+            "entries",
+            &entries
+                .on_this_day(month, day, tolerance)
+                .into_iter()
+                .map(|(_, entry)| self.dashboard_entry_preview(entry))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        Response::html(self.tera.render("list.html", &context).unwrap())
+    }
+
     fn dashboard_entry_preview(&self, entry: &Entry) -> String {
-        let content = entry.content().unwrap_or_default();
+        let content = entry.full_text();
         let preview = content;
+        let front_matter = entry.front_matter();
+        let title = match front_matter.title {
+            Some(ref title) => format!("{} – {}", entry.date_str(), title),
+            None => entry.date_str(),
+        };
 
         format!(
             r#"<div class="entry-preview">
                 <div style="font-weight: 600; color: #4facfe; margin-bottom: 0.5rem;">
                     <a href="/{}" style="text-decoration: none; color: inherit;">{}</a>
                 </div>
+                {}
                 <div style="color: #6c757d; line-height: 1.6;">
                     {}
                 </div>
             </div>"#,
             entry.date_str(),
-            entry.date_str(),
+            title,
+            self.front_matter_summary_html(entry),
             preview.replace("\n", "<br/>")
         )
     }
 
+    fn front_matter_summary_html(&self, entry: &Entry) -> String {
+        let front_matter = entry.front_matter();
+        let items = [
+            &front_matter.mood,
+            &front_matter.weather,
+            &front_matter.location,
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .chain(front_matter.people.iter().cloned())
+        .collect::<Vec<_>>();
+        if items.is_empty() {
+            return String::new();
+        }
+        format!(
+            r#"<div style="color: #8e9aa6; font-size: 0.85rem; margin-bottom: 0.5rem;">{}</div>"#,
+            items.join(" · ")
+        )
+    }
+
     fn entry_html(&self, entry: &Entry) -> String {
         let mut context = tera::Context::new();
+        context.insert("content", &render_text(&entry.body().unwrap_or_default()));
+        context.insert("date", &entry.date_str());
+        context.insert("upload_date", &entry.date_str());
         context.insert(
-            "content",
-            &entry.content().unwrap().replace("\n", "\n<br/>"),
+            "version",
+            &format!(
+                "{:016x}",
+                self.fingerprints
+                    .read()
+                    .unwrap()
+                    .get(&entry.date_str())
+                    .copied()
+                    .unwrap_or_default()
+            ),
         );
-        context.insert("date", &entry.date_str());
-        context.insert("pics", &self.pics_html(entry));
-        context.insert("audio", &self.audio_html(entry));
-        context.insert("lastfm", &self.lastfm_html(entry));
-        let prev = self.entries.prev(entry);
-        let next = self.entries.next(entry);
+        context.insert("meta", entry.front_matter());
+        context.insert("backlinks", &self.backlinks_html(entry));
+        context.insert(
+            "tags",
+            &entry
+                .tags()
+                .iter()
+                .map(|tag| self.tag_chip(tag, None))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        let capture_times = self.capture_times(entry);
+        self.insert_timeline(entry, &capture_times, &mut context);
+        context.insert(
+            "day_data",
+            &self
+                .providers
+                .iter()
+                .filter_map(|provider| provider.section_html(entry, &capture_times))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        let entries = self.entries();
+        let prev = entries.prev(entry);
+        let next = entries.next(entry);
         context.insert("link_entry", &self.entry_link(entry));
         context.insert("link_prev", &self.entry_link(prev.unwrap_or(entry)));
         context.insert("link_next", &self.entry_link(next.unwrap_or(entry)));
         self.tera.render("entry.html", &context).unwrap()
     }
 
-    fn pics_html(&self, entry: &Entry) -> String {
-        if entry.pics().is_empty() {
-            "".to_string()
-        } else {
-            entry
-                .pics()
-                .iter()
-                .map(|pic| {
-                    let mut context = tera::Context::new();
-                    context.insert("pic", pic);
-                    self.tera.render("pic.html", &context).unwrap()
-                })
-                .collect::<Vec<_>>()
-                .join("\n")
-        }
+    /// Returns the capture times of the pictures of an entry that were taken
+    /// on the day of the entry.
+    fn capture_times(&self, entry: &Entry) -> HashMap<PathBuf, chrono::NaiveDateTime> {
+        let journal = Path::new(config::JOURNAL_PATH);
+        entry
+            .pics()
+            .iter()
+            .filter_map(|pic| {
+                photo_info::photo_capture_time(&journal.join(pic))
+                    .filter(|time| time.date() == entry.date())
+                    .map(|time| (pic.clone(), time))
+            })
+            .collect()
     }
 
-    fn audio_html(&self, entry: &Entry) -> String {
-        if entry.audio().is_empty() {
-            "".to_string()
-        } else {
-            entry
-                .audio()
-                .iter()
-                .map(|audio| {
-                    let mut context = tera::Context::new();
-                    context.insert("audio", audio);
-                    self.tera.render("audio.html", &context).unwrap()
-                })
-                .collect::<Vec<_>>()
-                .join("\n")
+    /// Interleaves the timestamped notes of an entry with its pictures and
+    /// audio files by capture time. Everything without a known time on the
+    /// day of the entry is shown below the main text, as before.
+    fn insert_timeline(
+        &self,
+        entry: &Entry,
+        capture_times: &HashMap<PathBuf, chrono::NaiveDateTime>,
+        context: &mut tera::Context,
+    ) {
+        let journal = Path::new(config::JOURNAL_PATH);
+        let on_day = |time: Option<chrono::NaiveDateTime>| {
+            time.filter(|time| time.date() == entry.date())
+                .map(|time| time.time())
+        };
+        let mut timeline: Vec<(NaiveTime, String)> = vec![];
+        let mut notes = vec![];
+        for note in entry.notes() {
+            match note.time() {
+                Some(time) => timeline.push((time, self.note_html(note))),
+                None => notes.push(self.note_html(note)),
+            }
+        }
+        let mut pics = vec![];
+        for pic in entry.pics() {
+            match capture_times.get(pic) {
+                Some(time) => {
+                    timeline.push((time.time(), self.pics_html(entry, &[pic], capture_times)))
+                }
+                None => pics.push(pic),
+            }
         }
+        let mut audio = vec![];
+        for file in entry.audio() {
+            match on_day(dump_processor::audio_recording_time(&journal.join(file))) {
+                Some(time) => timeline.push((time, self.audio_html(entry, &[file]))),
+                None => audio.push(file),
+            }
+        }
+        timeline.sort_by_key(|(time, _)| *time);
+        let timeline = timeline
+            .into_iter()
+            .map(|(time, html)| {
+                format!(
+                    r#"<div class="timeline-item"><div class="timeline-time">{}</div><div class="timeline-body">{}</div></div>"#,
+                    time.format("%H:%M"),
+                    html
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        context.insert("timeline", &timeline);
+        context.insert("notes", &notes.join("\n"));
+        context.insert("pics", &self.pics_html(entry, &pics, capture_times));
+        context.insert("audio", &self.audio_html(entry, &audio));
     }
 
-    fn entry_link(&self, prev: &Entry) -> String {
-        prev.date_str()
+    fn note_html(&self, note: &Note) -> String {
+        let mut context = tera::Context::new();
+        context.insert("label", &note.label());
+        context.insert("content", &render_text(&note.body().unwrap_or_default()));
+        self.tera.render("note.html", &context).unwrap()
     }
 
-    fn lastfm_html(&self, entry: &Entry) -> String {
-        if let Some(ref analyzer) = self.lastfm {
-            let date_str = &entry.date_str();
-            let total_scrobbles = analyzer.get_total_scrobbles_for_date(date_str);
-
-            if total_scrobbles == 0 {
-                return String::new();
-            }
+    fn media_context(&self, entry: &Entry, file: &Path) -> tera::Context {
+        let info = entry.media_info(file);
+        let mut context = tera::Context::new();
+        context.insert("date", &entry.date_str());
+        context.insert("file", &escape_html(&entry.media_key(file)));
+        context.insert(
+            "caption",
+            &escape_html(info.caption.as_deref().unwrap_or("")),
+        );
+        context.insert("alt_text", &escape_html(info.alt.as_deref().unwrap_or("")));
+        context.insert(
+            "alt",
+            &escape_html(
+                info.alt
+                    .as_deref()
+                    .or(info.caption.as_deref())
+                    .unwrap_or("Journal image"),
+            ),
+        );
+        context.insert("people", &escape_html(&info.people.join(", ")));
+        context.insert("favourite", &info.favourite);
+        context
+    }
 
-            let top_tracks = analyzer.get_top_tracks_for_date(date_str, 5);
-            let top_albums = analyzer.get_top_albums_for_date(date_str, 5);
+    /// Renders pictures, each annotated by the providers with what they know
+    /// about the time it was taken.
+    fn pics_html(
+        &self,
+        entry: &Entry,
+        pics: &[&PathBuf],
+        capture_times: &HashMap<PathBuf, chrono::NaiveDateTime>,
+    ) -> String {
+        pics.iter()
+            .map(|pic| {
+                let mut context = self.media_context(entry, pic);
+                context.insert("pic", pic);
+                let annotations: Vec<String> = capture_times
+                    .get(*pic)
+                    .map(|time| {
+                        self.providers
+                            .iter()
+                            .filter_map(|provider| provider.photo_annotation(*time))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                context.insert("annotations", &annotations);
+                self.tera.render("pic.html", &context).unwrap()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 
-            let mut html = format!(
-                r#"<div class="lastfm-section">
-                    <h3>Music on {}</h3>
-                    <p class="total-tracks">{} tracks played</p>
-                    
-                    <div class="tabs">
-                        <button class="tab-btn active" onclick="switchTab(event, 'albums')">Top Albums</button>
-                        <button class="tab-btn" onclick="switchTab(event, 'tracks')">Top Tracks</button>
-                    </div>
-                    
-                    <div id="albums" class="tab-content active">"#,
-                date_str, total_scrobbles
-            );
+    fn audio_html(&self, entry: &Entry, audio: &[&PathBuf]) -> String {
+        audio
+            .iter()
+            .map(|audio| {
+                let mut context = self.media_context(entry, audio);
+                context.insert("audio", audio);
+                self.tera.render("audio.html", &context).unwrap()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 
-            // Albums tab
-            if !top_albums.is_empty() {
-                html.push_str(r#"<ul class="stats-list">"#);
-                for album in &top_albums {
-                    html.push_str(&format!(
-                        r#"<li><span class="item-name">{}</span><br><span class="artist-name">{}</span> <span class="play-count">({} plays)</span></li>"#,
-                        album.name, album.artist, album.play_count
-                    ));
-                }
-                html.push_str("</ul>");
-            }
-            html.push_str("</div>");
-
-            // Tracks tab
-            html.push_str(r#"<div id="tracks" class="tab-content">"#);
-            if !top_tracks.is_empty() {
-                html.push_str(r#"<ul class="stats-list">"#);
-                for track in &top_tracks {
-                    html.push_str(&format!(
-                        r#"<li><span class="item-name">{}</span><br><span class="artist-name">{}</span> <span class="play-count">({} plays)</span></li>"#,
-                        track.name, track.artist, track.play_count
-                    ));
-                }
-                html.push_str("</ul>");
-            }
-            html.push_str("</div>");
+    fn backlinks_html(&self, entry: &Entry) -> String {
+        self.entries()
+            .backlinks(entry)
+            .iter()
+            .map(|date| format!(r#"<li><a href="/{}">{}</a></li>"#, date, date))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 
-            html.push_str("</div>");
-            html
-        } else {
-            String::new()
-        }
+    fn entry_link(&self, prev: &Entry) -> String {
+        prev.date_str()
     }
 }
+
+fn render_text(text: &str) -> String {
+    links::render_links(text).replace("\n", "\n<br/>")
+}
+
+fn snippet_html(snippet: &[SnippetPart]) -> String {
+    snippet
+        .iter()
+        .map(|(text, highlighted)| {
+            let text = escape_html(text).replace('\n', "<br/>");
+            if *highlighted {
+                format!("<mark>{}</mark>", text)
+            } else {
+                text
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::editor;
+
+/// Name of the sidecar file in each date folder that holds the metadata of
+/// its pictures and audio files.
+pub const FILE_NAME: &str = "media.yaml";
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MediaInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+    /// Alternative text for pictures, shown when the image can't be.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alt: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub people: Vec<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub favourite: bool,
+}
+
+/// Maps the path of each media file relative to its date folder, such as
+/// `pics/lake.jpg`, to its metadata.
+pub type MediaInfos = BTreeMap<String, MediaInfo>;
+
+/// Reads the sidecar file of a date folder. A missing file means that no
+/// media file has any metadata yet.
+pub fn read(folder: &Path) -> Result<MediaInfos, Box<dyn Error>> {
+    match fs::read_to_string(folder.join(FILE_NAME)) {
+        Ok(content) if content.trim().is_empty() => Ok(MediaInfos::new()),
+        Ok(content) => Ok(serde_yaml::from_str(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(MediaInfos::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Replaces the metadata of `file` in the sidecar file of `folder`. Files
+/// without any metadata are left out, and the sidecar file is removed once
+/// it would be empty.
+pub fn update(folder: &Path, file: &str, info: MediaInfo) -> Result<(), Box<dyn Error>> {
+    let mut infos = read(folder)?;
+    if info == MediaInfo::default() {
+        infos.remove(file);
+    } else {
+        infos.insert(file.to_owned(), info);
+    }
+    let path = folder.join(FILE_NAME);
+    if infos.is_empty() {
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    } else {
+        editor::write_atomically(&path, &serde_yaml::to_string(&infos)?)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_the_sidecar_file_once_it_is_empty() {
+        let folder =
+            std::env::temp_dir().join(format!("journal-media-info-test-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let info = MediaInfo {
+            caption: Some("Sunset".to_owned()),
+            ..MediaInfo::default()
+        };
+        update(&folder, "pics/a.jpg", info.clone()).unwrap();
+        update(&folder, "pics/b.jpg", MediaInfo::default()).unwrap();
+        let infos = read(&folder).unwrap();
+        update(&folder, "pics/a.jpg", MediaInfo::default()).unwrap();
+        let removed = !folder.join(FILE_NAME).exists();
+        fs::remove_dir_all(&folder).unwrap();
+        assert_eq!(infos, MediaInfos::from([("pics/a.jpg".to_owned(), info)]));
+        assert!(removed);
+    }
+}
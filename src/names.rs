@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use crate::config;
+use crate::lastfm::Scrobble;
+
+/// Words marking a parenthesized or dash-separated suffix of an album or
+/// track name as a mere edition, such as "(Remastered 2011)" or "- Deluxe Edition".
+const EDITION_WORDS: [&str; 7] = [
+    "remaster",
+    "deluxe",
+    "expanded",
+    "anniversary",
+    "special edition",
+    "bonus track",
+    "mono version",
+];
+
+fn is_edition(suffix: &str) -> bool {
+    let suffix = suffix.to_lowercase();
+    EDITION_WORDS.iter().any(|word| suffix.contains(word))
+}
+
+/// Removes edition suffixes from the end of `name`, as long as something
+/// remains of it.
+fn strip_suffixes(name: &str) -> &str {
+    let mut name = name.trim();
+    loop {
+        let stripped = match name.chars().last() {
+            Some(close @ (')' | ']')) => {
+                let open = if close == ')' { '(' } else { '[' };
+                name.rfind(open)
+                    .filter(|start| is_edition(&name[*start..]))
+                    .map(|start| &name[..start])
+            }
+            _ => name
+                .rfind(" - ")
+                .filter(|start| is_edition(&name[*start..]))
+                .map(|start| &name[..start]),
+        };
+        match stripped.map(str::trim) {
+            Some(stripped) if !stripped.is_empty() => name = stripped,
+            _ => return name,
+        }
+    }
+}
+
+fn fold(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// The key under which different spellings of a name are counted together:
+/// case folded, without edition suffixes and with aliases resolved.
+pub fn key(name: &str) -> String {
+    let folded = fold(strip_suffixes(name));
+    match alias(&folded) {
+        Some(canonical) => fold(canonical),
+        None => folded,
+    }
+}
+
+/// Returns the canonical name configured for the folded `name`.
+fn alias(folded: &str) -> Option<&'static str> {
+    config::NAME_ALIASES
+        .iter()
+        .find(|(alias, _)| fold(alias) == folded)
+        .map(|(_, canonical)| *canonical)
+}
+
+/// Counts how often each spelling (without edition suffixes) occurs per key.
+#[derive(Debug, Default)]
+struct Spellings(HashMap<String, HashMap<String, usize>>);
+
+impl Spellings {
+    fn add(&mut self, name: &str) {
+        let spelling = strip_suffixes(name);
+        *self
+            .0
+            .entry(key(name))
+            .or_default()
+            .entry(spelling.to_owned())
+            .or_default() += 1;
+    }
+
+    /// Maps each key to its configured alias or else its most common
+    /// spelling, ties broken alphabetically.
+    fn canonical(self) -> HashMap<String, String> {
+        self.0
+            .into_iter()
+            .map(|(key, spellings)| {
+                let name = config::NAME_ALIASES
+                    .iter()
+                    .find(|(_, canonical)| fold(canonical) == key)
+                    .map(|(_, canonical)| canonical.to_string())
+                    .or_else(|| {
+                        spellings
+                            .into_iter()
+                            .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+                            .map(|(spelling, _)| spelling)
+                    })
+                    .unwrap_or_default();
+                (key, name)
+            })
+            .collect()
+    }
+}
+
+/// The canonical names of all artists, albums and tracks, so that differing
+/// spellings of the same name are aggregated and displayed as one.
+#[derive(Debug, Default)]
+pub struct CanonicalNames {
+    artists: HashMap<String, String>,
+    albums: HashMap<String, String>,
+    tracks: HashMap<String, String>,
+}
+
+impl CanonicalNames {
+    pub fn new<'a>(scrobbles: impl IntoIterator<Item = &'a Scrobble>) -> Self {
+        let mut artists = Spellings::default();
+        let mut albums = Spellings::default();
+        let mut tracks = Spellings::default();
+        for scrobble in scrobbles {
+            artists.add(&scrobble.artist);
+            albums.add(&scrobble.album);
+            tracks.add(&scrobble.track);
+        }
+        Self {
+            artists: artists.canonical(),
+            albums: albums.canonical(),
+            tracks: tracks.canonical(),
+        }
+    }
+
+    /// Replaces the names of `scrobble` by their canonical spelling.
+    pub fn apply(&self, scrobble: &mut Scrobble) {
+        for (name, canonical) in [
+            (&mut scrobble.artist, &self.artists),
+            (&mut scrobble.album, &self.albums),
+            (&mut scrobble.track, &self.tracks),
+        ] {
+            if let Some(canonical_name) = canonical.get(&key(name)) {
+                name.clone_from(canonical_name);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_edition_suffixes() {
+        assert_eq!(strip_suffixes("Album (Remastered 2011)"), "Album");
+        assert_eq!(strip_suffixes("Album - Deluxe Edition"), "Album");
+        assert_eq!(
+            strip_suffixes("Album [Bonus Track Version] (2016 Remaster)"),
+            "Album"
+        );
+        assert_eq!(strip_suffixes("Song - 2009 Remaster "), "Song");
+    }
+
+    #[test]
+    fn keeps_other_suffixes_and_names_that_are_only_a_suffix() {
+        assert_eq!(
+            strip_suffixes("Song (feat. Someone)"),
+            "Song (feat. Someone)"
+        );
+        assert_eq!(strip_suffixes("Song - Live"), "Song - Live");
+        assert_eq!(strip_suffixes("(Deluxe)"), "(Deluxe)");
+    }
+
+    #[test]
+    fn keys_ignore_case_whitespace_and_editions() {
+        assert_eq!(key("Album (Remastered 2011)"), "album");
+        assert_eq!(key("ALBUM  - Deluxe Edition"), "album");
+        assert_eq!(key(" The  Band "), "the band");
+        assert_ne!(key("Album"), key("Album 2"));
+    }
+
+    #[test]
+    fn canonical_names_use_most_common_spelling() {
+        let scrobble = |artist: &str, album: &str| Scrobble {
+            track: "Track".to_owned(),
+            artist: artist.to_owned(),
+            album: album.to_owned(),
+            date: 0,
+            extra: serde_json::Map::new(),
+        };
+        let scrobbles = [
+            scrobble("The Band", "Album (Remastered 2011)"),
+            scrobble("The Band", "Album"),
+            scrobble("the band", "album - Deluxe Edition"),
+        ];
+        let names = CanonicalNames::new(&scrobbles);
+        let mut renamed = scrobble("THE BAND", "ALBUM (Deluxe)");
+        names.apply(&mut renamed);
+        assert_eq!(renamed.artist, "The Band");
+        assert_eq!(renamed.album, "Album");
+    }
+}
@@ -0,0 +1,65 @@
+use chrono::NaiveDate;
+use rand::prelude::SliceRandom;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config;
+
+fn weather() -> String {
+    let Some(command) = config::WEATHER_COMMAND else {
+        return String::new();
+    };
+    match Command::new("sh").arg("-c").arg(command).output() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).trim().to_owned(),
+        Err(e) => {
+            eprintln!("Warning: Could not run weather command: {}", e);
+            String::new()
+        }
+    }
+}
+
+fn render_template(date: NaiveDate) -> String {
+    let prompt = config::WRITING_PROMPTS
+        .choose(&mut rand::thread_rng())
+        .copied()
+        .unwrap_or_default();
+    let mut content = config::ENTRY_TEMPLATE
+        .replace("{date}", &date.format("%Y-%m-%d").to_string())
+        .replace("{weekday}", &date.format("%A").to_string())
+        .replace("{prompt}", prompt);
+    // Only run the (potentially slow) weather command if the template uses
+    // it, and only for today, since it reports the current weather.
+    if content.contains("{weather}") {
+        let weather = if date == chrono::Local::now().date_naive() {
+            weather()
+        } else {
+            String::new()
+        };
+        content = content.replace("{weather}", &weather);
+    }
+    content
+}
+
+fn create(date: NaiveDate, content: impl FnOnce() -> String) -> std::io::Result<PathBuf> {
+    let date_folder = Path::new(config::JOURNAL_PATH).join(date.format("%Y-%m-%d").to_string());
+    fs::create_dir_all(&date_folder)?;
+    let entry_md_path = date_folder.join("entry.md");
+    if !entry_md_path.exists() {
+        fs::write(&entry_md_path, content())?;
+    }
+    Ok(entry_md_path)
+}
+
+/// Creates the folder and `entry.md` for `date` from the entry template,
+/// unless they already exist. Returns the path of `entry.md`.
+pub fn create_entry(date: NaiveDate) -> std::io::Result<PathBuf> {
+    create(date, || render_template(date))
+}
+
+/// Creates the folder and `entry.md` for `date` with just a heading, unless
+/// they already exist. Used for files imported into (possibly past) days,
+/// which the template's weather and writing prompt would not fit.
+pub fn create_stub(date: NaiveDate) -> std::io::Result<PathBuf> {
+    create(date, || format!("# {}\n\n", date.format("%Y-%m-%d")))
+}
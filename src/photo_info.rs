@@ -0,0 +1,117 @@
+use chrono::NaiveDateTime;
+use exif::{Exif, In, Tag, Value};
+use serde::Serialize;
+use std::path::Path;
+
+/// Where a picture was taken, in decimal degrees.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// The EXIF details of a picture shown in the info panel of the lightbox.
+#[derive(Debug, Default, Serialize)]
+pub struct PhotoInfo {
+    pub camera: Option<String>,
+    pub lens: Option<String>,
+    /// Exposure time, aperture, ISO and focal length, as far as known.
+    pub exposure: Option<String>,
+    pub time: Option<String>,
+    pub location: Option<Location>,
+}
+
+fn read_exif(path: &Path) -> Option<Exif> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut bufreader = std::io::BufReader::new(&file);
+    exif::Reader::new().read_from_container(&mut bufreader).ok()
+}
+
+fn ascii(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => values
+            .first()
+            .and_then(|value| std::str::from_utf8(value).ok())
+            .map(|value| value.trim_matches(|c: char| c == '\0' || c.is_whitespace()))
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_owned()),
+        _ => None,
+    }
+}
+
+fn display(exif: &Exif, tag: Tag) -> Option<String> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    Some(field.display_value().with_unit(exif).to_string())
+}
+
+/// Converts a GPS coordinate given as degrees, minutes and seconds into
+/// decimal degrees, negative for the southern and western hemispheres.
+fn coordinate(exif: &Exif, tag: Tag, ref_tag: Tag, negative_ref: &str) -> Option<f64> {
+    let Value::Rational(parts) = &exif.get_field(tag, In::PRIMARY)?.value else {
+        return None;
+    };
+    let [degrees, minutes, seconds] = parts.as_slice() else {
+        return None;
+    };
+    let value = degrees.to_f64() + minutes.to_f64() / 60.0 + seconds.to_f64() / 3600.0;
+    if ascii(exif, ref_tag).as_deref() == Some(negative_ref) {
+        Some(-value)
+    } else {
+        Some(value)
+    }
+}
+
+/// Returns the time a picture was taken, preferring the original capture
+/// time over the time the file was last changed or digitized.
+pub fn capture_time(exif: &Exif) -> Option<NaiveDateTime> {
+    [Tag::DateTimeOriginal, Tag::DateTime, Tag::DateTimeDigitized]
+        .into_iter()
+        .filter_map(|tag| ascii(exif, tag))
+        // EXIF dates are in format "YYYY:MM:DD HH:MM:SS"
+        .find_map(|date| NaiveDateTime::parse_from_str(&date, "%Y:%m:%d %H:%M:%S").ok())
+}
+
+/// Reads the capture time of a photo from its EXIF data.
+pub fn photo_capture_time(path: &Path) -> Option<NaiveDateTime> {
+    capture_time(&read_exif(path)?)
+}
+
+/// Reads the EXIF details of the picture at `path`. Pictures without EXIF
+/// data result in empty details.
+pub fn read(path: &Path) -> PhotoInfo {
+    let Some(exif) = read_exif(path) else {
+        return PhotoInfo::default();
+    };
+    let camera = match (ascii(&exif, Tag::Make), ascii(&exif, Tag::Model)) {
+        (Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => make.or(model),
+    };
+    let exposure = [
+        display(&exif, Tag::ExposureTime),
+        display(&exif, Tag::FNumber),
+        display(&exif, Tag::PhotographicSensitivity).map(|iso| format!("ISO {}", iso)),
+        display(&exif, Tag::FocalLength),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+    let location = coordinate(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, "S")
+        .zip(coordinate(
+            &exif,
+            Tag::GPSLongitude,
+            Tag::GPSLongitudeRef,
+            "W",
+        ))
+        .map(|(latitude, longitude)| Location {
+            latitude,
+            longitude,
+        });
+    PhotoInfo {
+        camera,
+        lens: ascii(&exif, Tag::LensModel),
+        exposure: Some(exposure.join(" · ")).filter(|exposure| !exposure.is_empty()),
+        time: capture_time(&exif).map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string()),
+        location,
+    }
+}
@@ -0,0 +1,115 @@
+use id3::TagLike;
+use serde::Serialize;
+use std::path::Path;
+
+use crate::config;
+use crate::dump_processor;
+use crate::entries::Entry;
+use crate::web::url_segment;
+
+/// An audio file of an entry together with the metadata shown in the player.
+#[derive(Debug, Serialize)]
+pub struct Track {
+    /// Path of the file relative to the journal, which is also its URL path.
+    pub file: String,
+    pub date: String,
+    pub title: String,
+    /// Length in seconds, if the ID3 tag of the file knows it.
+    pub duration: Option<u32>,
+    /// Time of day the file was recorded at, as `HH:MM`.
+    pub recorded: Option<String>,
+}
+
+fn track(entry: &Entry, file: &Path) -> Track {
+    let path = Path::new(config::JOURNAL_PATH).join(file);
+    let tag = id3::Tag::read_from_path(&path).ok();
+    let title = tag
+        .as_ref()
+        .and_then(|tag| tag.title())
+        .map(|title| title.to_owned())
+        .or(entry.media_info(file).caption)
+        .or_else(|| {
+            file.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_default();
+    Track {
+        file: file.to_string_lossy().into_owned(),
+        date: entry.date_str(),
+        title,
+        // TLEN is given in milliseconds.
+        duration: tag
+            .as_ref()
+            .and_then(|tag| tag.duration())
+            .map(|ms| ms / 1000),
+        recorded: dump_processor::audio_recording_time(&path)
+            .map(|time| time.format("%H:%M").to_string()),
+    }
+}
+
+/// Returns the audio files of all `entries` in chronological order: by
+/// date, then by recording time, then by name for files without one.
+pub fn tracks<'a>(entries: impl Iterator<Item = &'a Entry>) -> Vec<Track> {
+    let mut tracks = vec![];
+    for entry in entries {
+        let mut day: Vec<Track> = entry
+            .audio()
+            .iter()
+            .map(|file| track(entry, file))
+            .collect();
+        day.sort_by(|a, b| {
+            (a.recorded.is_none(), &a.recorded, &a.file).cmp(&(
+                b.recorded.is_none(),
+                &b.recorded,
+                &b.file,
+            ))
+        });
+        tracks.extend(day);
+    }
+    tracks
+}
+
+/// Formats `tracks` as an extended M3U playlist. `base_url` is prepended to
+/// the file paths so that external players can fetch them from the server.
+pub fn m3u(tracks: &[Track], base_url: &str) -> String {
+    let mut playlist = "#EXTM3U\n".to_owned();
+    for track in tracks {
+        let url_path = track
+            .file
+            .split('/')
+            .map(url_segment)
+            .collect::<Vec<_>>()
+            .join("/");
+        playlist.push_str(&format!(
+            "#EXTINF:{},{} - {}\n{}/{}\n",
+            track.duration.map(|d| d as i64).unwrap_or(-1),
+            track.date,
+            // A line break would end the entry early.
+            track.title.replace(['\r', '\n'], " "),
+            base_url,
+            url_path
+        ));
+    }
+    playlist
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_paths_and_keeps_titles_on_one_line() {
+        let tracks = [Track {
+            file: "2023-07-02/audio/lake #1 ü.mp3".to_owned(),
+            date: "2023-07-02".to_owned(),
+            title: "At the\r\nlake".to_owned(),
+            duration: Some(61),
+            recorded: None,
+        }];
+        assert_eq!(
+            m3u(&tracks, "http://localhost:8000"),
+            "#EXTM3U\n#EXTINF:61,2023-07-02 - At the  lake\n\
+             http://localhost:8000/2023%2D07%2D02/audio/lake%20%231%20%C3%BC%2Emp3\n"
+        );
+    }
+}
@@ -0,0 +1,444 @@
+use chrono::{DateTime, NaiveDateTime};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::lastfm::{LastFmData, Scrobble};
+
+/// Plays shorter than this are not counted, following the Last.fm rule
+/// that a track has to be played for at least 30 seconds to be scrobbled.
+const MIN_PLAY_MS: i64 = 30_000;
+
+/// The listening history exports that can be read into scrobbles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// The JSON export of lastfmstats.com, which is also the format synced
+    /// scrobbles are stored in.
+    LastFmStats,
+    /// A CSV export of Last.fm scrobbles, either with `artist, album,
+    /// track, date` columns and no header, or with a header naming a `uts`
+    /// column.
+    LastFmCsv,
+    /// A ListenBrainz listens export, as a JSON array or JSON lines.
+    ListenBrainz,
+    /// Spotify's "Extended streaming history" JSON.
+    Spotify,
+    /// The `Apple Music Play Activity.csv` of Apple's privacy data export.
+    AppleMusic,
+}
+
+/// Returns the lowercase extension of `path` if exports may have it, so
+/// that other files don't need to be read to rule them out.
+fn export_extension(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    matches!(extension.as_str(), "csv" | "json" | "jsonl").then_some(extension)
+}
+
+impl Format {
+    /// Guesses the format of an export from its file name and content.
+    pub fn detect(path: &Path, content: &str) -> Option<Self> {
+        let extension = export_extension(path)?;
+        let start = content.trim_start();
+        match extension.as_str() {
+            "csv" => {
+                let header = start.lines().next().unwrap_or_default();
+                if header.contains("Song Name") || header.contains("Content Name") {
+                    Some(Format::AppleMusic)
+                } else {
+                    Some(Format::LastFmCsv)
+                }
+            }
+            "json" | "jsonl" => {
+                if start.starts_with('{') && start.contains("\"scrobbles\"") {
+                    Some(Format::LastFmStats)
+                } else if start.contains("\"ms_played\"") {
+                    Some(Format::Spotify)
+                } else if start.contains("\"listened_at\"") {
+                    Some(Format::ListenBrainz)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Reads all scrobbles from the export at `path`. If `path` is a folder,
+/// all exports in it are read, since some services split their export
+/// over several files.
+pub fn import(path: &Path) -> Result<Vec<Scrobble>, Box<dyn Error>> {
+    if !path.is_dir() {
+        return import_file(path);
+    }
+    let mut files: Vec<_> = fs::read_dir(path)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| {
+            path.is_file()
+                && !path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        })
+        .collect();
+    files.sort();
+    let mut scrobbles = vec![];
+    for file in files.iter().filter(|file| export_extension(file).is_some()) {
+        let content = match fs::read_to_string(file) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Warning: Could not read {}: {}", file.display(), e);
+                continue;
+            }
+        };
+        if Format::detect(file, &content).is_some() {
+            match parse(file, &content) {
+                Ok(parsed) => scrobbles.extend(parsed),
+                Err(e) => eprintln!("Warning: Could not parse {}: {}", file.display(), e),
+            }
+        }
+    }
+    Ok(scrobbles)
+}
+
+/// Returns the format of the export at `path`, if it is a single file of a
+/// known format.
+pub fn file_format(path: &Path) -> Option<Format> {
+    if path.is_dir() {
+        return None;
+    }
+    Format::detect(path, &fs::read_to_string(path).ok()?)
+}
+
+fn import_file(path: &Path) -> Result<Vec<Scrobble>, Box<dyn Error>> {
+    parse(path, &fs::read_to_string(path)?)
+}
+
+fn parse(path: &Path, content: &str) -> Result<Vec<Scrobble>, Box<dyn Error>> {
+    match Format::detect(path, content) {
+        Some(Format::LastFmStats) => parse_lastfm_stats(content),
+        Some(Format::LastFmCsv) => parse_lastfm_csv(content),
+        Some(Format::ListenBrainz) => parse_listenbrainz(content),
+        Some(Format::Spotify) => parse_spotify(content),
+        Some(Format::AppleMusic) => parse_apple_music(content),
+        None => Err(format!("{}: unknown export format", path.display()).into()),
+    }
+}
+
+fn rfc3339_ms(timestamp: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|dt| dt.timestamp_millis())
+}
+
+fn parse_lastfm_stats(content: &str) -> Result<Vec<Scrobble>, Box<dyn Error>> {
+    Ok(serde_json::from_str::<LastFmData>(content)?.scrobbles)
+}
+
+fn parse_lastfm_csv(content: &str) -> Result<Vec<Scrobble>, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let mut records = reader.records();
+    let Some(first) = records.next().transpose()? else {
+        return Ok(vec![]);
+    };
+    let columns: HashMap<String, usize> = first
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.trim().to_lowercase(), i))
+        .collect();
+    // Exports with a header name their columns, the others are positional.
+    let (has_header, artist, album, track, date) = if columns.contains_key("uts") {
+        let column = |name: &str| {
+            columns
+                .get(name)
+                .copied()
+                .ok_or(format!("missing {} column", name))
+        };
+        (
+            true,
+            column("artist")?,
+            column("album")?,
+            column("track")?,
+            column("uts")?,
+        )
+    } else {
+        (columns.contains_key("artist"), 0, 1, 2, 3)
+    };
+    let mut scrobbles = vec![];
+    for record in std::iter::once(Ok(first))
+        .chain(records)
+        .skip(has_header as usize)
+    {
+        let record = record?;
+        let field = |i: usize| record.get(i).unwrap_or_default().to_owned();
+        let date = field(date);
+        let timestamp = match date.parse::<i64>() {
+            Ok(seconds) => Some(seconds * 1000),
+            Err(_) => NaiveDateTime::parse_from_str(&date, "%d %b %Y %H:%M")
+                .ok()
+                .map(|dt| dt.and_utc().timestamp_millis()),
+        };
+        // Tracks played while the scrobbler was offline can lack a date.
+        let Some(timestamp) = timestamp else {
+            continue;
+        };
+        scrobbles.push(Scrobble {
+            track: field(track),
+            artist: field(artist),
+            album: field(album),
+            date: timestamp,
+            extra: serde_json::Map::new(),
+        });
+    }
+    Ok(scrobbles)
+}
+
+#[derive(Deserialize)]
+struct ListenBrainzMetadata {
+    artist_name: String,
+    track_name: String,
+    #[serde(default)]
+    release_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ListenBrainzListen {
+    listened_at: i64,
+    track_metadata: ListenBrainzMetadata,
+}
+
+fn parse_listenbrainz(content: &str) -> Result<Vec<Scrobble>, Box<dyn Error>> {
+    let listens: Vec<ListenBrainzListen> = if content.trim_start().starts_with('[') {
+        serde_json::from_str(content)?
+    } else {
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?
+    };
+    Ok(listens
+        .into_iter()
+        .map(|listen| Scrobble {
+            track: listen.track_metadata.track_name,
+            artist: listen.track_metadata.artist_name,
+            album: listen.track_metadata.release_name.unwrap_or_default(),
+            date: listen.listened_at * 1000,
+            extra: serde_json::Map::new(),
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct SpotifyPlay {
+    ts: String,
+    ms_played: i64,
+    master_metadata_track_name: Option<String>,
+    master_metadata_album_artist_name: Option<String>,
+    master_metadata_album_album_name: Option<String>,
+}
+
+fn parse_spotify(content: &str) -> Result<Vec<Scrobble>, Box<dyn Error>> {
+    let plays: Vec<SpotifyPlay> = serde_json::from_str(content)?;
+    Ok(plays
+        .into_iter()
+        .filter(|play| play.ms_played >= MIN_PLAY_MS)
+        .filter_map(|play| {
+            // Podcast episodes have no track name.
+            Some(Scrobble {
+                track: play.master_metadata_track_name?,
+                artist: play.master_metadata_album_artist_name.unwrap_or_default(),
+                album: play.master_metadata_album_album_name.unwrap_or_default(),
+                // The timestamp is when the track stopped playing.
+                date: rfc3339_ms(&play.ts)? - play.ms_played,
+                extra: serde_json::Map::new(),
+            })
+        })
+        .collect())
+}
+
+fn parse_apple_music(content: &str) -> Result<Vec<Scrobble>, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let headers = reader.headers()?.clone();
+    let column = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| headers.iter().position(|header| header == *name))
+    };
+    let track = column(&["Song Name", "Content Name"]).ok_or("missing song name column")?;
+    let artist = column(&["Artist Name", "Container Artist Name"]);
+    let album = column(&["Album Name", "Container Album Name"]);
+    let time = column(&[
+        "Event Start Timestamp",
+        "Event End Timestamp",
+        "Play Date Time",
+    ])
+    .ok_or("missing timestamp column")?;
+    let duration = column(&["Play Duration Milliseconds"]);
+    let mut scrobbles = vec![];
+    for record in reader.records() {
+        let record = record?;
+        let field = |i: Option<usize>| i.and_then(|i| record.get(i)).unwrap_or_default().to_owned();
+        let played_ms = field(duration).parse::<i64>().ok();
+        if played_ms.is_some_and(|ms| ms < MIN_PLAY_MS) {
+            continue;
+        }
+        let (track, Some(date)) = (field(Some(track)), rfc3339_ms(&field(Some(time)))) else {
+            continue;
+        };
+        if track.is_empty() {
+            continue;
+        }
+        scrobbles.push(Scrobble {
+            track,
+            artist: field(artist),
+            album: field(album),
+            date,
+            extra: serde_json::Map::new(),
+        });
+    }
+    Ok(scrobbles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(scrobbles: Vec<Scrobble>) -> Vec<(String, String, String, i64)> {
+        scrobbles
+            .into_iter()
+            .map(|s| (s.artist, s.album, s.track, s.date))
+            .collect()
+    }
+
+    fn scrobble(
+        artist: &str,
+        album: &str,
+        track: &str,
+        date: i64,
+    ) -> (String, String, String, i64) {
+        (artist.to_owned(), album.to_owned(), track.to_owned(), date)
+    }
+
+    #[test]
+    fn detects_formats_by_extension_and_content() {
+        let detect = |name: &str, content: &str| Format::detect(Path::new(name), content);
+        assert_eq!(
+            detect("a.json", r#" {"username": "u", "scrobbles": []}"#),
+            Some(Format::LastFmStats)
+        );
+        assert_eq!(
+            detect("a.CSV", "Artist,Album,Track,Date"),
+            Some(Format::LastFmCsv)
+        );
+        assert_eq!(
+            detect("a.csv", "Song Name,Event Start Timestamp"),
+            Some(Format::AppleMusic)
+        );
+        assert_eq!(
+            detect("a.jsonl", r#"{"listened_at": 1}"#),
+            Some(Format::ListenBrainz)
+        );
+        assert_eq!(
+            detect("a.json", r#"[{"ms_played": 1}]"#),
+            Some(Format::Spotify)
+        );
+        assert_eq!(detect("a.json", "{}"), None);
+        assert_eq!(detect("a.zip", "scrobbles"), None);
+    }
+
+    #[test]
+    fn imports_folders_skipping_other_unreadable_and_invalid_files() {
+        let folder =
+            std::env::temp_dir().join(format!("journal-import-test-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("export.zip"), [0x50, 0x4b, 0xff, 0xfe]).unwrap();
+        fs::write(folder.join("broken.csv"), [0xff, 0xfe]).unwrap();
+        fs::write(folder.join("invalid.json"), r#"[{"ms_played": "1"}]"#).unwrap();
+        fs::write(folder.join("scrobbles.csv"), "A,B,T,1688293800\n").unwrap();
+        let scrobbles = import(&folder);
+        fs::remove_dir_all(&folder).unwrap();
+        assert_eq!(
+            summary(scrobbles.unwrap()),
+            vec![scrobble("A", "B", "T", 1688293800000)]
+        );
+    }
+
+    #[test]
+    fn parses_lastfm_stats() {
+        let content = r#"{"username": "u", "scrobbles": [{"track": "T", "artist": "A", "album": "B", "date": 1000}]}"#;
+        assert_eq!(
+            summary(parse_lastfm_stats(content).unwrap()),
+            vec![scrobble("A", "B", "T", 1000)]
+        );
+    }
+
+    #[test]
+    fn parses_lastfm_csv_with_and_without_header() {
+        let positional = "A,B,T,02 Jul 2023 10:30\nA,B,Offline,\n";
+        assert_eq!(
+            summary(parse_lastfm_csv(positional).unwrap()),
+            vec![scrobble("A", "B", "T", 1688293800000)]
+        );
+        let with_header = "uts,utc_time,artist,album,track\n1688293800,ignored,A,B,T\n";
+        assert_eq!(
+            summary(parse_lastfm_csv(with_header).unwrap()),
+            vec![scrobble("A", "B", "T", 1688293800000)]
+        );
+    }
+
+    #[test]
+    fn parses_listenbrainz_arrays_and_lines() {
+        let listen = r#"{"listened_at": 1688293800, "track_metadata": {"artist_name": "A", "track_name": "T", "release_name": "B"}}"#;
+        let without_release = r#"{"listened_at": 1688293900, "track_metadata": {"artist_name": "A", "track_name": "U"}}"#;
+        let expected = vec![
+            scrobble("A", "B", "T", 1688293800000),
+            scrobble("A", "", "U", 1688293900000),
+        ];
+        assert_eq!(
+            summary(parse_listenbrainz(&format!("[{}, {}]", listen, without_release)).unwrap()),
+            expected
+        );
+        assert_eq!(
+            summary(parse_listenbrainz(&format!("{}\n\n{}\n", listen, without_release)).unwrap()),
+            expected
+        );
+    }
+
+    #[test]
+    fn parses_spotify_at_start_time_without_short_plays_and_podcasts() {
+        let content = r#"[
+            {"ts": "2023-07-02T10:33:20Z", "ms_played": 200000, "master_metadata_track_name": "T",
+             "master_metadata_album_artist_name": "A", "master_metadata_album_album_name": "B"},
+            {"ts": "2023-07-02T10:35:00Z", "ms_played": 5000, "master_metadata_track_name": "Skipped",
+             "master_metadata_album_artist_name": "A", "master_metadata_album_album_name": "B"},
+            {"ts": "2023-07-02T10:40:00Z", "ms_played": 900000, "master_metadata_track_name": null,
+             "master_metadata_album_artist_name": null, "master_metadata_album_album_name": null}
+        ]"#;
+        assert_eq!(
+            summary(parse_spotify(content).unwrap()),
+            vec![scrobble("A", "B", "T", 1688293800000)]
+        );
+    }
+
+    #[test]
+    fn parses_apple_music_without_short_plays() {
+        let content =
+            "Song Name,Artist Name,Album Name,Event Start Timestamp,Play Duration Milliseconds\n\
+                       T,A,B,2023-07-02T10:30:00.000Z,200000\n\
+                       Skipped,A,B,2023-07-02T10:35:00.000Z,5000\n\
+                       ,A,B,2023-07-02T10:40:00.000Z,200000\n\
+                       Undated,A,B,,200000\n";
+        assert_eq!(
+            summary(parse_apple_music(content).unwrap()),
+            vec![scrobble("A", "B", "T", 1688293800000)]
+        );
+    }
+}
@@ -0,0 +1,485 @@
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+
+use crate::entries::{Entries, Entry};
+use crate::tags;
+
+/// Number of bytes of context shown before the first match in a snippet.
+const SNIPPET_CONTEXT_BEFORE: usize = 60;
+/// Maximum length of a snippet in bytes.
+const SNIPPET_LENGTH: usize = 200;
+
+/// Splits `text` into lowercase words together with their byte range in `text`.
+fn tokenize(text: &str) -> Vec<(String, Range<usize>)> {
+    let mut tokens = vec![];
+    let mut start = None;
+    for (i, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        if c.is_alphanumeric() {
+            start.get_or_insert(i);
+        } else if let Some(start) = start.take() {
+            tokens.push((text[start..i].to_lowercase(), start..i));
+        }
+    }
+    tokens
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Word(String),
+    Prefix(String),
+    Phrase(Vec<String>),
+}
+
+impl Term {
+    /// Turns a piece of the query into a term. Anything that tokenizes into
+    /// more than one word (`"lake house"`, `don't`) is treated as a phrase.
+    fn from_text(text: &str) -> Option<Self> {
+        let mut words: Vec<String> = tokenize(text).into_iter().map(|(word, _)| word).collect();
+        match words.len() {
+            0 => None,
+            1 => Some(Term::Word(words.remove(0))),
+            _ => Some(Term::Phrase(words)),
+        }
+    }
+
+    fn highlights(&self, word: &str) -> bool {
+        match self {
+            Term::Word(w) => w == word,
+            Term::Prefix(prefix) => word.starts_with(prefix.as_str()),
+            Term::Phrase(words) => words.iter().any(|w| w == word),
+        }
+    }
+}
+
+/// A parsed search query. Besides plain words, the query syntax supports
+/// `"quoted phrases"`, `prefix*` queries, `tag:name` or `#name` tag filters
+/// and `from:YYYY-MM-DD` / `to:YYYY-MM-DD` date filters.
+#[derive(Debug, Default, Clone)]
+pub struct Query {
+    terms: Vec<Term>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub tags: Vec<String>,
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Self {
+        let mut result = Self::default();
+        let mut rest = query.trim();
+        while !rest.is_empty() {
+            if let Some(quoted) = rest.strip_prefix('"') {
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                result.terms.extend(Term::from_text(&quoted[..end]));
+                rest = quoted.get(end + 1..).unwrap_or("");
+            } else {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                result.add_word(&rest[..end]);
+                rest = &rest[end..];
+            }
+            rest = rest.trim_start();
+        }
+        result
+    }
+
+    fn add_word(&mut self, word: &str) {
+        let parse_date = |date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+        if let Some(date) = word.strip_prefix("from:") {
+            self.from = parse_date(date);
+        } else if let Some(date) = word.strip_prefix("to:") {
+            self.to = parse_date(date);
+        } else if let Some(tag) = word.strip_prefix("tag:").or(word.strip_prefix('#')) {
+            self.tags.push(tags::normalize(tag));
+        } else if let Some(prefix) = word.strip_suffix('*') {
+            match Term::from_text(prefix) {
+                Some(Term::Word(prefix)) => self.terms.push(Term::Prefix(prefix)),
+                term => self.terms.extend(term),
+            }
+        } else {
+            self.terms.extend(Term::from_text(word));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.from.is_none() && self.to.is_none() && self.tags.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Document {
+    date: String,
+    tags: Vec<String>,
+    text: String,
+}
+
+/// A piece of a snippet, which is highlighted if it matches the query.
+pub type SnippetPart = (String, bool);
+
+#[derive(Debug)]
+pub struct SearchResult {
+    pub date: String,
+    pub score: usize,
+    pub snippet: Vec<SnippetPart>,
+}
+
+/// An inverted index over the text of all entries, their front matter and
+/// any additional per-day text (such as the artists listened to that day).
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    documents: Vec<Document>,
+    /// Maps each word to the documents containing it and the word positions within them.
+    postings: BTreeMap<String, BTreeMap<usize, Vec<usize>>>,
+}
+
+impl SearchIndex {
+    pub fn build(entries: &Entries, extra_text: &HashMap<String, Vec<String>>) -> Self {
+        let mut index = Self::default();
+        for entry in entries.iter() {
+            let extra = extra_text
+                .get(&entry.date_str())
+                .map(|text| text.as_slice())
+                .unwrap_or_default();
+            index.add(entry, extra);
+        }
+        index
+    }
+
+    /// Returns a copy in which the entries with the dates in `changed` are
+    /// indexed anew. The text of all other entries is taken over as it is.
+    pub fn update(
+        &self,
+        entries: &Entries,
+        extra_text: &HashMap<String, Vec<String>>,
+        changed: &[String],
+    ) -> Self {
+        let documents: HashMap<&str, &Document> = self
+            .documents
+            .iter()
+            .map(|document| (document.date.as_str(), document))
+            .collect();
+        let mut index = Self::default();
+        for entry in entries.iter() {
+            let date = entry.date_str();
+            match documents.get(date.as_str()) {
+                Some(document) if !changed.contains(&date) => {
+                    let document = (*document).clone();
+                    index.add_document(document.date, document.tags, document.text);
+                }
+                _ => {
+                    let extra = extra_text
+                        .get(&date)
+                        .map(|text| text.as_slice())
+                        .unwrap_or_default();
+                    index.add(entry, extra);
+                }
+            }
+        }
+        index
+    }
+
+    fn add(&mut self, entry: &Entry, extra: &[String]) {
+        let front_matter = entry.front_matter();
+        let text = [
+            front_matter.title.clone(),
+            Some(entry.full_text()),
+            front_matter.mood.clone(),
+            front_matter.weather.clone(),
+            front_matter.location.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .chain(front_matter.people.iter().cloned())
+        .chain(entry.tags().iter().cloned())
+        .chain(entry.media_infos().values().flat_map(|info| {
+            info.caption
+                .iter()
+                .chain(info.alt.iter())
+                .chain(info.people.iter())
+                .cloned()
+        }))
+        .chain(extra.iter().cloned())
+        .collect::<Vec<_>>()
+        .join("\n");
+        self.add_document(entry.date_str(), entry.tags().to_vec(), text);
+    }
+
+    fn add_document(&mut self, date: String, tags: Vec<String>, text: String) {
+        let doc = self.documents.len();
+        for (position, (word, _)) in tokenize(&text).into_iter().enumerate() {
+            self.postings
+                .entry(word)
+                .or_default()
+                .entry(doc)
+                .or_default()
+                .push(position);
+        }
+        self.documents.push(Document { date, tags, text });
+    }
+
+    /// Returns the number of occurrences of `term` in each document containing it.
+    fn matches(&self, term: &Term) -> BTreeMap<usize, usize> {
+        let mut result = BTreeMap::new();
+        match term {
+            Term::Word(word) => {
+                if let Some(docs) = self.postings.get(word) {
+                    result.extend(docs.iter().map(|(doc, positions)| (*doc, positions.len())));
+                }
+            }
+            Term::Prefix(prefix) => {
+                for (_, docs) in self
+                    .postings
+                    .range(prefix.clone()..)
+                    .take_while(|(word, _)| word.starts_with(prefix.as_str()))
+                {
+                    for (doc, positions) in docs {
+                        *result.entry(*doc).or_default() += positions.len();
+                    }
+                }
+            }
+            Term::Phrase(words) => {
+                let postings: Option<Vec<_>> =
+                    words.iter().map(|word| self.postings.get(word)).collect();
+                let Some(postings) = postings else {
+                    return result;
+                };
+                for (doc, positions) in postings[0] {
+                    let count = positions
+                        .iter()
+                        .filter(|start| {
+                            postings[1..].iter().enumerate().all(|(i, docs)| {
+                                docs.get(doc).is_some_and(|positions| {
+                                    positions.binary_search(&(*start + i + 1)).is_ok()
+                                })
+                            })
+                        })
+                        .count();
+                    if count > 0 {
+                        result.insert(*doc, count);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn passes_filters(&self, document: &Document, query: &Query) -> bool {
+        let date = NaiveDate::parse_from_str(&document.date, "%Y-%m-%d").ok();
+        let in_range = match date {
+            Some(date) => {
+                query.from.is_none_or(|from| date >= from) && query.to.is_none_or(|to| date <= to)
+            }
+            None => query.from.is_none() && query.to.is_none(),
+        };
+        in_range && query.tags.iter().all(|tag| document.tags.contains(tag))
+    }
+
+    /// Returns all documents matching every term and filter of the query,
+    /// best matches first and newest first among equally good matches.
+    pub fn search(&self, query: &Query) -> Vec<SearchResult> {
+        if query.is_empty() {
+            return vec![];
+        }
+        let mut scores: Option<BTreeMap<usize, usize>> = None;
+        for term in &query.terms {
+            let matches = self.matches(term);
+            scores = Some(match scores {
+                None => matches,
+                Some(scores) => scores
+                    .into_iter()
+                    .filter_map(|(doc, score)| matches.get(&doc).map(|count| (doc, score + count)))
+                    .collect(),
+            });
+        }
+        let scores =
+            scores.unwrap_or_else(|| (0..self.documents.len()).map(|doc| (doc, 0)).collect());
+        let mut results: Vec<SearchResult> = scores
+            .into_iter()
+            .filter(|(doc, _)| self.passes_filters(&self.documents[*doc], query))
+            .map(|(doc, score)| SearchResult {
+                date: self.documents[doc].date.clone(),
+                score,
+                snippet: snippet(&self.documents[doc].text, &query.terms),
+            })
+            .collect();
+        results.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| b.date.cmp(&a.date)));
+        results
+    }
+}
+
+fn char_boundary_before(text: &str, mut index: usize) -> usize {
+    index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// Cuts a window around the first match out of `text` and splits it into
+/// highlighted and plain parts.
+fn snippet(text: &str, terms: &[Term]) -> Vec<SnippetPart> {
+    let highlighted: Vec<Range<usize>> = tokenize(text)
+        .into_iter()
+        .filter(|(word, _)| terms.iter().any(|term| term.highlights(word)))
+        .map(|(_, range)| range)
+        .collect();
+    let first = highlighted.first().map(|range| range.start).unwrap_or(0);
+    let start = char_boundary_before(text, first.saturating_sub(SNIPPET_CONTEXT_BEFORE));
+    let end = char_boundary_before(text, start + SNIPPET_LENGTH);
+    let mut parts = vec![];
+    if start > 0 {
+        parts.push(("…".to_owned(), false));
+    }
+    let mut position = start;
+    for range in highlighted {
+        if range.start < start || range.end > end {
+            continue;
+        }
+        parts.push((text[position..range.start].to_owned(), false));
+        parts.push((text[range.clone()].to_owned(), true));
+        position = range.end;
+    }
+    parts.push((text[position..end].to_owned(), false));
+    if end < text.len() {
+        parts.push(("…".to_owned(), false));
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(documents: &[(&str, &[&str], &str)]) -> SearchIndex {
+        let mut index = SearchIndex::default();
+        for (date, tags, text) in documents {
+            index.add_document(
+                date.to_string(),
+                tags.iter().map(|tag| tag.to_string()).collect(),
+                text.to_string(),
+            );
+        }
+        index
+    }
+
+    fn dates(index: &SearchIndex, query: &str) -> Vec<String> {
+        index
+            .search(&Query::parse(query))
+            .into_iter()
+            .map(|result| result.date)
+            .collect()
+    }
+
+    #[test]
+    fn parses_words_phrases_prefixes_and_filters() {
+        let query = Query::parse(
+            r#"Lake "old House" swim* #Summer tag:Road-Trip from:2023-07-01 to:2023-07-31"#,
+        );
+        assert_eq!(
+            query.terms,
+            vec![
+                Term::Word("lake".to_owned()),
+                Term::Phrase(vec!["old".to_owned(), "house".to_owned()]),
+                Term::Prefix("swim".to_owned()),
+            ]
+        );
+        assert_eq!(query.tags, vec!["summer", "road-trip"]);
+        assert_eq!(query.from, NaiveDate::from_ymd_opt(2023, 7, 1));
+        assert_eq!(query.to, NaiveDate::from_ymd_opt(2023, 7, 31));
+    }
+
+    #[test]
+    fn parses_unclosed_quotes_and_words_with_punctuation_as_phrases() {
+        let query = Query::parse(r#"don't "lake house"#);
+        assert_eq!(
+            query.terms,
+            vec![
+                Term::Phrase(vec!["don".to_owned(), "t".to_owned()]),
+                Term::Phrase(vec!["lake".to_owned(), "house".to_owned()]),
+            ]
+        );
+        assert!(Query::parse("  ").is_empty());
+        assert!(Query::parse("from:invalid").is_empty());
+    }
+
+    #[test]
+    fn ranks_by_number_of_matches_then_newest_first() {
+        let index = index(&[
+            ("2023-01-01", &[], "lake"),
+            ("2023-01-02", &[], "lake lake lake"),
+            ("2023-01-03", &[], "lake"),
+            ("2023-01-04", &[], "forest"),
+        ]);
+        assert_eq!(
+            dates(&index, "lake"),
+            vec!["2023-01-02", "2023-01-03", "2023-01-01"]
+        );
+    }
+
+    #[test]
+    fn requires_every_term() {
+        let index = index(&[
+            ("2023-01-01", &[], "lake and forest"),
+            ("2023-01-02", &[], "lake only"),
+        ]);
+        assert_eq!(dates(&index, "Lake FOREST"), vec!["2023-01-01"]);
+    }
+
+    #[test]
+    fn matches_phrases_only_with_adjacent_words() {
+        let index = index(&[
+            ("2023-01-01", &[], "the lake house"),
+            ("2023-01-02", &[], "a house by the lake"),
+        ]);
+        assert_eq!(dates(&index, r#""lake house""#), vec!["2023-01-01"]);
+    }
+
+    #[test]
+    fn matches_prefixes() {
+        let index = index(&[
+            ("2023-01-01", &[], "swimming"),
+            ("2023-01-02", &[], "swam"),
+            ("2023-01-03", &[], "swim swimmer"),
+        ]);
+        assert_eq!(dates(&index, "swim*"), vec!["2023-01-03", "2023-01-01"]);
+    }
+
+    #[test]
+    fn filters_by_tags_and_dates() {
+        let index = index(&[
+            ("2023-06-30", &["summer"], "lake"),
+            ("2023-07-01", &["summer"], "lake"),
+            ("2023-07-02", &[], "lake"),
+            ("notes", &["summer"], "lake"),
+        ]);
+        assert_eq!(
+            dates(&index, "lake #summer"),
+            vec!["notes", "2023-07-01", "2023-06-30"]
+        );
+        assert_eq!(
+            dates(&index, "lake from:2023-07-01"),
+            vec!["2023-07-02", "2023-07-01"]
+        );
+        assert_eq!(
+            dates(&index, "tag:summer to:2023-06-30"),
+            vec!["2023-06-30"]
+        );
+    }
+
+    #[test]
+    fn highlights_matches_in_snippet() {
+        let text = format!("{}The lake was cold.", "x ".repeat(50));
+        let snippet = snippet(&text, &[Term::Word("lake".to_owned())]);
+        assert_eq!(snippet.first(), Some(&("…".to_owned(), false)));
+        assert!(snippet.contains(&("lake".to_owned(), true)));
+        assert_eq!(
+            snippet
+                .iter()
+                .filter(|(_, highlighted)| *highlighted)
+                .count(),
+            1
+        );
+    }
+}
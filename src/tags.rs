@@ -0,0 +1,56 @@
+/// Brings a tag into the form used for indexing and in `/tags/{tag}` urls.
+pub fn normalize(tag: &str) -> String {
+    tag.trim()
+        .trim_start_matches('#')
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+/// Returns all inline `#hashtags` in `content`. A `#` only starts a tag at
+/// the beginning of a word, so markdown headings (`# Title`) and anchors
+/// in urls are not mistaken for tags. Purely numeric tags (`#1`) are skipped.
+pub fn find_hashtags(content: &str) -> Vec<String> {
+    let mut tags = vec![];
+    let mut prev = None;
+    for (i, c) in content.char_indices() {
+        let at_word_start = prev
+            .map(|p: char| p.is_whitespace() || p == '(')
+            .unwrap_or(true);
+        prev = Some(c);
+        if c != '#' || !at_word_start {
+            continue;
+        }
+        let rest = &content[i + 1..];
+        let end = rest.find(|c| !is_tag_char(c)).unwrap_or(rest.len());
+        let tag = rest[..end].trim_end_matches(['-', '_']);
+        if !tag.is_empty() && !tag.chars().all(|c| c.is_ascii_digit()) {
+            tags.push(normalize(tag));
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_tags() {
+        assert_eq!(normalize(" #Road  Trip "), "road-trip");
+        assert_eq!(normalize("Summer"), "summer");
+    }
+
+    #[test]
+    fn finds_hashtags_at_word_starts() {
+        assert_eq!(
+            find_hashtags("# Title\nA #Swim at the lake (#summer_2023) with #1, see x.com/#anchor and #trip-."),
+            vec!["swim", "summer_2023", "trip"]
+        );
+    }
+}
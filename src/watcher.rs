@@ -0,0 +1,181 @@
+use notify::{Event, RecursiveMode, Watcher};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::media_info;
+
+/// Maps the name of each folder in the journal to its fingerprint.
+pub type Fingerprints = HashMap<String, u64>;
+
+fn hash_file(path: &Path, hasher: &mut DefaultHasher) {
+    if let Ok(metadata) = fs::metadata(path) {
+        metadata.modified().ok().hash(hasher);
+        metadata.len().hash(hasher);
+    }
+}
+
+/// Returns a value that changes whenever the text, one of the notes or the
+/// media metadata of the entry in `folder` is modified or media files are
+/// added, removed or renamed.
+pub fn folder_fingerprint(folder: &Path) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    if let Ok(read_dir) = fs::read_dir(folder) {
+        let mut texts: Vec<_> = read_dir
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|path| {
+                path.extension().is_some_and(|ext| ext == "md")
+                    || path.ends_with(media_info::FILE_NAME)
+            })
+            .collect();
+        texts.sort();
+        for path in texts {
+            path.hash(&mut hasher);
+            hash_file(&path, &mut hasher);
+        }
+    }
+    for subfolder in ["pics", "audio"] {
+        if let Ok(read_dir) = fs::read_dir(folder.join(subfolder)) {
+            let mut names: Vec<_> = read_dir
+                .filter_map(|e| e.ok())
+                .map(|e| e.file_name())
+                .collect();
+            names.sort();
+            names.hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+pub fn journal_fingerprints(journal: &Path) -> Fingerprints {
+    match fs::read_dir(journal) {
+        Ok(read_dir) => read_dir
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .map(|e| {
+                (
+                    e.file_name().to_string_lossy().into_owned(),
+                    folder_fingerprint(&e.path()),
+                )
+            })
+            .collect(),
+        Err(_) => Fingerprints::new(),
+    }
+}
+
+/// Returns the fingerprint of the folder `name` in the journal, or `None`
+/// if there is no such folder (anymore).
+pub fn fingerprint(journal: &Path, name: &str) -> Option<u64> {
+    let folder = journal.join(name);
+    folder.is_dir().then(|| folder_fingerprint(&folder))
+}
+
+/// Returns the names of the folders whose `current` fingerprint differs
+/// from the `known` one, in alphabetical order.
+pub fn changed_folders(
+    known: &Fingerprints,
+    current: &HashMap<String, Option<u64>>,
+) -> Vec<String> {
+    let mut changed: Vec<String> = current
+        .iter()
+        .filter(|(name, fingerprint)| known.get(*name) != fingerprint.as_ref())
+        .map(|(name, _)| name.clone())
+        .collect();
+    changed.sort();
+    changed
+}
+
+/// Returns the name of the file or folder in the root of `journal` that
+/// `path` is in.
+fn top_level_name(journal: &Path, path: &Path) -> Option<String> {
+    match path.strip_prefix(journal).ok()?.components().next()? {
+        Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+        _ => None,
+    }
+}
+
+/// Watches the journal for changes in a background thread and calls
+/// `on_change` with the names of the files and folders in its root that
+/// anything changed in. Changes coming in within `delay` of each other are
+/// reported together. Whether a folder really changed is left to the
+/// caller, whose own changes to the journal shouldn't count as changes.
+pub fn watch(
+    journal: PathBuf,
+    delay: Duration,
+    on_change: impl Fn(Vec<String>) + Send + 'static,
+) -> notify::Result<()> {
+    let (sender, receiver) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(&journal, RecursiveMode::Recursive)?;
+    std::thread::spawn(move || {
+        let _watcher = watcher;
+        while let Ok(event) = receiver.recv() {
+            let mut names = BTreeSet::new();
+            let mut add = |event: notify::Result<Event>| {
+                if let Ok(event) = event {
+                    names.extend(
+                        event
+                            .paths
+                            .iter()
+                            .filter_map(|path| top_level_name(&journal, path)),
+                    );
+                }
+            };
+            add(event);
+            while let Ok(event) = receiver.recv_timeout(delay) {
+                add(event);
+            }
+            if !names.is_empty() {
+                on_change(names.into_iter().collect());
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_added_removed_and_changed_folders() {
+        let known = Fingerprints::from([
+            ("2023-07-01".to_owned(), 1),
+            ("2023-07-02".to_owned(), 2),
+            ("2023-07-03".to_owned(), 3),
+        ]);
+        let current = HashMap::from([
+            ("2023-07-01".to_owned(), Some(1)),
+            ("2023-07-02".to_owned(), None),
+            ("2023-07-03".to_owned(), Some(4)),
+            ("2023-07-04".to_owned(), Some(5)),
+            ("lastfmstats-user.json".to_owned(), None),
+        ]);
+        assert_eq!(
+            changed_folders(&known, &current),
+            vec!["2023-07-02", "2023-07-03", "2023-07-04"]
+        );
+        let unchanged = HashMap::from([("2023-07-01".to_owned(), Some(1))]);
+        assert!(changed_folders(&known, &unchanged).is_empty());
+    }
+
+    #[test]
+    fn finds_the_top_level_folder_of_changed_paths() {
+        let journal = Path::new("/journal");
+        assert_eq!(
+            top_level_name(journal, Path::new("/journal/2023-07-01/pics/a.jpg")).as_deref(),
+            Some("2023-07-01")
+        );
+        assert_eq!(
+            top_level_name(journal, Path::new("/journal/notes.md")).as_deref(),
+            Some("notes.md")
+        );
+        assert_eq!(top_level_name(journal, journal), None);
+        assert_eq!(top_level_name(journal, Path::new("/elsewhere/a.md")), None);
+    }
+}
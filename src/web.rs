@@ -0,0 +1,21 @@
+use chrono::NaiveDate;
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Percent-encodes `text` for use as a single segment of a URL path.
+pub fn url_segment(text: &str) -> String {
+    rouille::percent_encoding::utf8_percent_encode(
+        text,
+        rouille::percent_encoding::NON_ALPHANUMERIC,
+    )
+    .to_string()
+}
+
+pub fn parse_date_param(date: Option<String>) -> Option<NaiveDate> {
+    date.and_then(|date| NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok())
+}